@@ -1,3 +1,6 @@
+use std::fmt;
+
+use omnitrace::describe::Node;
 use omnitrace::prelude::*;
 use omnitrace_math::prelude::*;
 
@@ -16,6 +19,13 @@ impl<V: Real + MulAdd<Output = V>, O, T: Trace<Vec2<V>, O>> Trace<Vec2<V>, O> fo
 		})
 	}
 }
+impl<T: Describe, V: fmt::Debug> Describe for Trans<T, V> {
+	fn describe(&self) -> Node {
+		Node::new("Trans")
+			.param("backward", format!("{:?}", self.backward))
+			.child(self.parent.describe())
+	}
+}
 
 pub trait TraceExtVec2Transform<V: Real + MulAdd<Output = V>, O>: Trace<Vec2<V>, O> {
 	fn trans(self, m: Mat3<V>) -> Trans<Self, V>
@@ -74,6 +84,14 @@ macro_rules! impl_ext_compositing {
 					)
 				}
 			}
+			impl<T: Describe, U: Describe> Describe for $struct<T, U> {
+				fn describe(&self) -> Node {
+					Node::new(stringify!($struct))
+						.param("mode", stringify!($base::$func))
+						.child(self.top.describe())
+						.child(self.bottom.describe())
+				}
+			}
 		)*
 		pub trait TraceExtCompositing<I>: Trace<I, Color> {$(
 			fn $then_blend<T: Trace<I, Color>>(self, top: T) -> $struct<T, Self>
@@ -172,6 +190,11 @@ impl<V: Real + MulAdd<Output = V>, T: Trace<Vec2<V>, V>> Trace<Vec2<V>, V> for S
 		}
 	}
 }
+impl<T: Describe> Describe for Sdf2Fac<T> {
+	fn describe(&self) -> Node {
+		Node::new("Sdf2Fac").child(self.parent.describe())
+	}
+}
 
 #[derive(Debug, Clone, Copy)]
 #[repr(transparent)]
@@ -200,6 +223,11 @@ impl<V: Real + MulAdd<Output = V>, T: Trace<Vec2<V>, V>> Trace<Vec2<V>, V> for S
 		})
 	}
 }
+impl<T: Describe> Describe for Ssaa4<T> {
+	fn describe(&self) -> Node {
+		Node::new("Ssaa4").child(self.parent.describe())
+	}
+}
 
 pub trait TraceExtSdf<V>: Trace<Vec2<V>, V> {
 	/// Marching-square based anti-aliasing
@@ -218,3 +246,405 @@ pub trait TraceExtSdf<V>: Trace<Vec2<V>, V> {
 	}
 }
 impl<V: Real, T: Trace<Vec2<V>, V>> TraceExtSdf<V> for T {}
+
+fn sdf_union<V: Real>(a: V, b: V) -> V {
+	a.min(b)
+}
+fn sdf_intersect<V: Real>(a: V, b: V) -> V {
+	a.max(b)
+}
+fn sdf_subtract<V: Real>(a: V, b: V) -> V {
+	a.max(-b)
+}
+fn sdf_xor<V: Real>(a: V, b: V) -> V {
+	a.min(b).max(-a.max(b))
+}
+/// quadratic polynomial smooth min, `k` is the width of the blended region
+fn sdf_smooth_union<V: Real>(a: V, b: V, k: V) -> V {
+	if k <= V::zero() {
+		return a.min(b);
+	}
+	let h = (k - (a - b).abs()).max(V::zero()) / k;
+	let v4 = (V::one() + V::one()) * (V::one() + V::one());
+	a.min(b) - h * h * k / v4
+}
+fn sdf_smooth_intersect<V: Real>(a: V, b: V, k: V) -> V {
+	-sdf_smooth_union(-a, -b, k)
+}
+fn sdf_smooth_subtract<V: Real>(a: V, b: V, k: V) -> V {
+	sdf_smooth_intersect(a, -b, k)
+}
+fn sdf_smooth_xor<V: Real>(a: V, b: V, k: V) -> V {
+	sdf_smooth_intersect(sdf_smooth_union(a, b, k), -sdf_smooth_intersect(a, b, k), k)
+}
+
+macro_rules! impl_ext_sdf_ops {
+	($($op:ident $struct:ident $func:ident, $smooth_op:ident $smooth_struct:ident $smooth_func:ident;)*) => {
+		$(
+			#[derive(Debug, Clone, Copy)]
+			pub struct $struct<T, U> {
+				a: T,
+				b: U,
+			}
+			impl<V: Real, T: Trace<Vec2<V>, V>, U: Trace<Vec2<V>, V>> Trace<Vec2<V>, V> for $struct<T, U> {
+				type Cache = (T::Cache, U::Cache);
+				fn trace(&self, input: Vec2<V>, cache: &mut Self::Cache) -> V {
+					$func(
+						self.a.trace(input, &mut cache.0),
+						self.b.trace(input, &mut cache.1),
+					)
+				}
+			}
+			impl<T: Describe, U: Describe> Describe for $struct<T, U> {
+				fn describe(&self) -> Node {
+					Node::new(stringify!($struct))
+						.child(self.a.describe())
+						.child(self.b.describe())
+				}
+			}
+
+			#[derive(Debug, Clone, Copy)]
+			pub struct $smooth_struct<T, U, V> {
+				a: T,
+				b: U,
+				k: V,
+			}
+			impl<V: Real, T: Trace<Vec2<V>, V>, U: Trace<Vec2<V>, V>> Trace<Vec2<V>, V> for $smooth_struct<T, U, V> {
+				type Cache = (T::Cache, U::Cache);
+				fn trace(&self, input: Vec2<V>, cache: &mut Self::Cache) -> V {
+					$smooth_func(
+						self.a.trace(input, &mut cache.0),
+						self.b.trace(input, &mut cache.1),
+						self.k,
+					)
+				}
+			}
+			impl<T: Describe, U: Describe, V: fmt::Debug> Describe for $smooth_struct<T, U, V> {
+				fn describe(&self) -> Node {
+					Node::new(stringify!($smooth_struct))
+						.param("k", format!("{:?}", self.k))
+						.child(self.a.describe())
+						.child(self.b.describe())
+				}
+			}
+		)*
+		/// combines distance fields, so the result can still be anti-aliased as one shape
+		pub trait TraceExtSdfOps<V: Real>: Trace<Vec2<V>, V> {$(
+			fn $op<T: Trace<Vec2<V>, V>>(self, other: T) -> $struct<Self, T>
+			where
+				Self: Sized,
+			{
+				$struct { a: self, b: other }
+			}
+			/// `k` is the distance over which the edges are blended, `0` is the same as the sharp version
+			fn $smooth_op<T: Trace<Vec2<V>, V>>(self, other: T, k: V) -> $smooth_struct<Self, T, V>
+			where
+				Self: Sized,
+			{
+				$smooth_struct { a: self, b: other, k }
+			}
+		)*}
+		impl<V: Real, T: Trace<Vec2<V>, V>> TraceExtSdfOps<V> for T {}
+	};
+}
+impl_ext_sdf_ops! {
+	union     SdfUnion     sdf_union,     smooth_union     SdfSmoothUnion     sdf_smooth_union;
+	intersect SdfIntersect sdf_intersect, smooth_intersect SdfSmoothIntersect sdf_smooth_intersect;
+	subtract  SdfSubtract  sdf_subtract,  smooth_subtract  SdfSmoothSubtract  sdf_smooth_subtract;
+	xor       SdfXor       sdf_xor,       smooth_xor       SdfSmoothXor       sdf_smooth_xor;
+}
+
+/// the distance shrunk by `radius`, rounding every corner
+#[derive(Debug, Clone, Copy)]
+pub struct SdfRound<T, V> {
+	parent: T,
+	radius: V,
+}
+impl<V: Real, T: Trace<Vec2<V>, V>> Trace<Vec2<V>, V> for SdfRound<T, V> {
+	type Cache = T::Cache;
+	fn trace(&self, input: Vec2<V>, cache: &mut Self::Cache) -> V {
+		self.parent.trace(input, cache) - self.radius
+	}
+}
+impl<T: Describe, V: fmt::Debug> Describe for SdfRound<T, V> {
+	fn describe(&self) -> Node {
+		Node::new("SdfRound")
+			.param("radius", format!("{:?}", self.radius))
+			.child(self.parent.describe())
+	}
+}
+
+/// how wide a line or shell is
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Width<V> {
+	/// in the units of the shape
+	Units(V),
+	/// in output pixels, read from [`crate::SCALE`] while tracing, so it doesn't change with zoom
+	Pixels(V),
+}
+impl<V: Real> Width<V> {
+	pub(crate) fn half(self) -> V {
+		let two = V::one() + V::one();
+		match self {
+			Self::Units(width) => width / two,
+			Self::Pixels(width) => {
+				let footprint = crate::SCALE.get().determinant().abs().sqrt();
+				width / two * V::from(footprint).unwrap()
+			}
+		}
+	}
+}
+impl<V> From<V> for Width<V> {
+	fn from(width: V) -> Self {
+		Self::Units(width)
+	}
+}
+impl<V: fmt::Debug> Width<V> {
+	pub(crate) fn describe(&self) -> String {
+		match self {
+			Self::Units(width) => format!("{width:?}"),
+			Self::Pixels(width) => format!("{width:?}px"),
+		}
+	}
+}
+
+/// only a shell of `thickness` around the edge
+#[derive(Debug, Clone, Copy)]
+pub struct SdfOnion<T, V> {
+	parent: T,
+	thickness: Width<V>,
+}
+impl<V: Real, T: Trace<Vec2<V>, V>> Trace<Vec2<V>, V> for SdfOnion<T, V> {
+	type Cache = T::Cache;
+	fn trace(&self, input: Vec2<V>, cache: &mut Self::Cache) -> V {
+		self.parent.trace(input, cache).abs() - self.thickness.half()
+	}
+}
+impl<T: Describe, V: fmt::Debug> Describe for SdfOnion<T, V> {
+	fn describe(&self) -> Node {
+		Node::new("SdfOnion")
+			.param("thickness", self.thickness.describe())
+			.child(self.parent.describe())
+	}
+}
+
+/// cells are centered on multiples of `period`, `limit` is the number of copies on each side of the origin.
+/// only translates, so `SCALE` stays the same
+#[derive(Debug, Clone, Copy)]
+pub struct SdfRepeat<T, V> {
+	parent: T,
+	period: Vec2<V>,
+	limit: Option<Vec2<V>>,
+}
+impl<V: Real, T: Trace<Vec2<V>, V>> Trace<Vec2<V>, V> for SdfRepeat<T, V> {
+	type Cache = T::Cache;
+	fn trace(&self, input: Vec2<V>, cache: &mut Self::Cache) -> V {
+		let cell = input.map2(self.period, |v, p| match p > V::zero() {
+			true => (v / p).round(),
+			false => V::zero(),
+		});
+		let cell = match self.limit {
+			Some(limit) => cell.map2(limit, |v, l| v.max(-l).min(l)),
+			None => cell,
+		};
+		self.parent.trace(input - self.period * cell, cache)
+	}
+}
+impl<T: Describe, V: fmt::Debug> Describe for SdfRepeat<T, V> {
+	fn describe(&self) -> Node {
+		let node = Node::new("SdfRepeat").param("period", format!("{:?}", self.period));
+		match &self.limit {
+			Some(limit) => node.param("limit", format!("{limit:?}")),
+			None => node,
+		}
+		.child(self.parent.describe())
+	}
+}
+
+/// the positive half of each axis in `axes` is reflected onto the negative one
+#[derive(Debug, Clone, Copy)]
+pub struct SdfMirror<T> {
+	parent: T,
+	axes: Vec2<bool>,
+}
+impl<V: Real, T: Trace<Vec2<V>, V>> Trace<Vec2<V>, V> for SdfMirror<T> {
+	type Cache = T::Cache;
+	fn trace(&self, input: Vec2<V>, cache: &mut Self::Cache) -> V {
+		let flip = input.map2(self.axes, |v, axis| axis && v < V::zero());
+		if !flip.reduce_or() {
+			return self.parent.trace(input, cache);
+		}
+		let sign = flip.map(|v| if v { -1.0 } else { 1.0f64 });
+		let old = crate::SCALE.get();
+		crate::SCALE.with(old * Mat2::<f64>::scaling_2d(sign), || {
+			self.parent
+				.trace(input.map2(flip, |v, f| if f { -v } else { v }), cache)
+		})
+	}
+}
+impl<T: Describe> Describe for SdfMirror<T> {
+	fn describe(&self) -> Node {
+		Node::new("SdfMirror")
+			.param("x", self.axes.x)
+			.param("y", self.axes.y)
+			.child(self.parent.describe())
+	}
+}
+
+/// `count` copies around the origin, the parent is traced in the sector around the positive x axis
+#[derive(Debug, Clone, Copy)]
+pub struct SdfRepeatPolar<T> {
+	parent: T,
+	count: u32,
+}
+impl<V: Real, T: Trace<Vec2<V>, V>> Trace<Vec2<V>, V> for SdfRepeatPolar<T> {
+	type Cache = T::Cache;
+	fn trace(&self, input: Vec2<V>, cache: &mut Self::Cache) -> V {
+		let sector = std::f64::consts::TAU / self.count.max(1) as f64;
+		let angle = input.y.atan2(input.x).to_f64().unwrap();
+		let rotation = -(angle / sector + 0.5).floor() * sector;
+		if rotation == 0.0 {
+			return self.parent.trace(input, cache);
+		}
+		let (sin, cos) = rotation.sin_cos();
+		let (sin_v, cos_v): (V, V) = (NumCast::from(sin).unwrap(), NumCast::from(cos).unwrap());
+		let local = Vec2::new(
+			input.x * cos_v - input.y * sin_v,
+			input.x * sin_v + input.y * cos_v,
+		);
+		let old = crate::SCALE.get();
+		crate::SCALE.with(old * Mat2::rotation_z(rotation), || {
+			self.parent.trace(local, cache)
+		})
+	}
+}
+impl<T: Describe> Describe for SdfRepeatPolar<T> {
+	fn describe(&self) -> Node {
+		Node::new("SdfRepeatPolar")
+			.param("count", self.count)
+			.child(self.parent.describe())
+	}
+}
+
+/// a swirl, the domain is rotated around the origin by `rate` radians per unit of distance from it.
+/// the distance is no longer exact, `SCALE` gets the local stretch
+#[derive(Debug, Clone, Copy)]
+pub struct SdfTwist<T, V> {
+	parent: T,
+	rate: V,
+}
+impl<V: Real, T: Trace<Vec2<V>, V>> Trace<Vec2<V>, V> for SdfTwist<T, V> {
+	type Cache = T::Cache;
+	fn trace(&self, input: Vec2<V>, cache: &mut Self::Cache) -> V {
+		let radius = input.magnitude();
+		let (sin, cos) = (self.rate * radius).sin_cos();
+		let local = Vec2::new(input.x * cos - input.y * sin, input.x * sin + input.y * cos);
+		// jacobian of `input -> local`, the rotation plus how the angle changes with the radius
+		let rotation = Mat2::rotation_z((self.rate * radius).to_f64().unwrap());
+		let jacobian = match radius > V::zero() {
+			true => {
+				let turn = Vec2::new(-local.y, local.x).map(|v| v.to_f64().unwrap());
+				let grad = (input * self.rate / radius).map(|v| v.to_f64().unwrap());
+				rotation
+					+ Mat2::new(
+						turn.x * grad.x,
+						turn.x * grad.y,
+						turn.y * grad.x,
+						turn.y * grad.y,
+					)
+			}
+			false => rotation,
+		};
+		let old = crate::SCALE.get();
+		crate::SCALE.with(old * jacobian, || self.parent.trace(local, cache))
+	}
+}
+impl<T: Describe, V: fmt::Debug> Describe for SdfTwist<T, V> {
+	fn describe(&self) -> Node {
+		Node::new("SdfTwist")
+			.param("rate", format!("{:?}", self.rate))
+			.child(self.parent.describe())
+	}
+}
+
+/// reshapes a distance field, domain changes update [`crate::SCALE`] so anti-aliasing inside stays correct
+pub trait TraceExtSdfModifiers<V: Real>: Trace<Vec2<V>, V> {
+	fn round(self, radius: V) -> SdfRound<Self, V>
+	where
+		Self: Sized,
+	{
+		SdfRound {
+			parent: self,
+			radius,
+		}
+	}
+	/// `thickness` is in the units of the shape unless it's [`Width::Pixels`]
+	fn onion(self, thickness: impl Into<Width<V>>) -> SdfOnion<Self, V>
+	where
+		Self: Sized,
+	{
+		SdfOnion {
+			parent: self,
+			thickness: thickness.into(),
+		}
+	}
+	/// infinitely, an axis with a period of `0` isn't repeated
+	fn repeat(self, period: Vec2<V>) -> SdfRepeat<Self, V>
+	where
+		Self: Sized,
+	{
+		SdfRepeat {
+			parent: self,
+			period,
+			limit: None,
+		}
+	}
+	/// `limit` copies on each side of the original, per axis
+	fn repeat_limited(self, period: Vec2<V>, limit: Vec2<V>) -> SdfRepeat<Self, V>
+	where
+		Self: Sized,
+	{
+		SdfRepeat {
+			parent: self,
+			period,
+			limit: Some(limit),
+		}
+	}
+	/// `x` becomes `|x|`
+	fn mirror_x(self) -> SdfMirror<Self>
+	where
+		Self: Sized,
+	{
+		SdfMirror {
+			parent: self,
+			axes: Vec2::new(true, false),
+		}
+	}
+	/// `y` becomes `|y|`
+	fn mirror_y(self) -> SdfMirror<Self>
+	where
+		Self: Sized,
+	{
+		SdfMirror {
+			parent: self,
+			axes: Vec2::new(false, true),
+		}
+	}
+	fn repeat_polar(self, count: u32) -> SdfRepeatPolar<Self>
+	where
+		Self: Sized,
+	{
+		SdfRepeatPolar {
+			parent: self,
+			count,
+		}
+	}
+	/// `rate` is in radians per unit of distance from the origin
+	fn twist(self, rate: V) -> SdfTwist<Self, V>
+	where
+		Self: Sized,
+	{
+		SdfTwist { parent: self, rate }
+	}
+}
+impl<V: Real, T: Trace<Vec2<V>, V>> TraceExtSdfModifiers<V> for T {}
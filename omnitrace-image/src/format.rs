@@ -0,0 +1,171 @@
+//! encoders for [`Image::encode`]
+
+use std::io::{self, Read, Write};
+use std::path::Path;
+
+use omnitrace_math::prelude::*;
+
+use crate::image::to_srgba8;
+use crate::Image;
+
+/// how [`Image::encode`] writes pixels
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Format {
+	/// 8-bit sRGB with alpha
+	#[default]
+	Png,
+	/// 16-bit sRGB with alpha
+	Png16,
+	/// linear 32-bit float RGB, alpha is dropped
+	Pfm,
+	/// linear 32-bit float RGBA, OpenEXR
+	Exr,
+	/// 8-bit sRGB, binary PPM, alpha is dropped
+	Ppm,
+	/// 8-bit sRGB with alpha, PAM
+	Pam,
+}
+impl Format {
+	/// guessed from the file extension, `.png` is always 8-bit
+	pub fn from_path(path: impl AsRef<Path>) -> Option<Self> {
+		let ext = path.as_ref().extension()?.to_str()?.to_ascii_lowercase();
+		match ext.as_str() {
+			"png" => Some(Self::Png),
+			"pfm" => Some(Self::Pfm),
+			"exr" => Some(Self::Exr),
+			"ppm" => Some(Self::Ppm),
+			"pam" => Some(Self::Pam),
+			_ => None,
+		}
+	}
+}
+
+pub(crate) fn encode(image: &Image, mut w: impl Write, format: Format) -> io::Result<()> {
+	let size = image.size();
+	match format {
+		Format::Png => {
+			let pixels = image.to_srgba8();
+			write_png(
+				w,
+				size,
+				png::BitDepth::Eight,
+				palette::cast::into_component_slice(&pixels),
+			)
+		}
+		Format::Png16 => {
+			let data = image
+				.pixels()
+				.iter()
+				.flat_map(|v| {
+					let color: palette::Srgb<f32> = palette::Srgb::from_linear(v.color);
+					[color.red, color.green, color.blue, v.alpha]
+				})
+				.flat_map(|v| ((v.clamp(0.0, 1.0) * 65535.0).round() as u16).to_be_bytes())
+				.collect::<Vec<_>>();
+			write_png(w, size, png::BitDepth::Sixteen, &data)
+		}
+		Format::Pfm => {
+			// negative scale means little endian, rows go bottom to top
+			write!(w, "PF\n{} {}\n-1.0\n", size.w, size.h)?;
+			for row in image.pixels().chunks(size.w.max(1) as usize).rev() {
+				let data = row
+					.iter()
+					.flat_map(|v| [v.red, v.green, v.blue])
+					.flat_map(f32::to_le_bytes)
+					.collect::<Vec<_>>();
+				w.write_all(&data)?;
+			}
+			Ok(())
+		}
+		Format::Exr => {
+			use exr::prelude::{SpecificChannels, WritableImage};
+			let width = size.w as usize;
+			let channels = SpecificChannels::rgba(|pos: exr::math::Vec2<usize>| {
+				let v = image.pixels()[pos.y() * width + pos.x()];
+				(v.red, v.green, v.blue, v.alpha)
+			});
+			let exr = exr::image::Image::from_channels((width, size.h as usize), channels);
+			// exr needs to seek, so encode in memory first
+			let mut buffer = io::Cursor::new(Vec::new());
+			exr.write()
+				.to_buffered(&mut buffer)
+				.map_err(io::Error::other)?;
+			w.write_all(buffer.get_ref())
+		}
+		Format::Ppm => {
+			write!(w, "P6\n{} {}\n255\n", size.w, size.h)?;
+			let data = image
+				.pixels()
+				.iter()
+				.flat_map(|v| {
+					let v = to_srgba8(*v);
+					[v.red, v.green, v.blue]
+				})
+				.collect::<Vec<_>>();
+			w.write_all(&data)
+		}
+		Format::Pam => {
+			write!(
+				w,
+				"P7\nWIDTH {}\nHEIGHT {}\nDEPTH 4\nMAXVAL 255\nTUPLTYPE RGB_ALPHA\nENDHDR\n",
+				size.w, size.h
+			)?;
+			w.write_all(palette::cast::into_component_slice(&image.to_srgba8()))
+		}
+	}
+}
+
+fn write_png(
+	w: impl Write,
+	size: Extent2<u32>,
+	depth: png::BitDepth,
+	data: &[u8],
+) -> io::Result<()> {
+	let mut encoder = png::Encoder::new(w, size.w, size.h);
+	encoder.set_color(png::ColorType::Rgba);
+	encoder.set_depth(depth);
+	encoder.set_source_gamma(png::ScaledFloat::from_scaled(45455));
+	let mut writer = encoder.write_header()?;
+	writer.write_image_data(data)?;
+	Ok(writer.finish()?)
+}
+
+pub(crate) fn decode_png(r: impl Read) -> io::Result<Image> {
+	let mut decoder = png::Decoder::new(r);
+	// palettes and low bit depths become 8-bit, 16-bit stays
+	decoder.set_transformations(png::Transformations::EXPAND);
+	let mut reader = decoder.read_info()?;
+	let mut data = vec![0; reader.output_buffer_size()];
+	let info = reader.next_frame(&mut data)?;
+	let channels = info.color_type.samples();
+	let samples = data[..info.buffer_size()]
+		.chunks(info.line_size)
+		.flat_map(|line| match info.bit_depth {
+			png::BitDepth::Sixteen => line
+				.chunks_exact(2)
+				.take(info.width as usize * channels)
+				.map(|v| u16::from_be_bytes([v[0], v[1]]) as f32 / 65535.0)
+				.collect::<Vec<_>>(),
+			_ => line
+				.iter()
+				.take(info.width as usize * channels)
+				.map(|&v| v as f32 / 255.0)
+				.collect(),
+		})
+		.collect::<Vec<_>>();
+	let pixels = samples
+		.chunks_exact(channels)
+		.map(|v| {
+			let (rgb, alpha) = match *v {
+				[l] => ([l, l, l], 1.0),
+				[l, a] => ([l, l, l], a),
+				[r, g, b] => ([r, g, b], 1.0),
+				[r, g, b, a] => ([r, g, b], a),
+				_ => unreachable!("png has at most 4 channels"),
+			};
+			let color = palette::Srgb::new(rgb[0], rgb[1], rgb[2]).into_linear();
+			Color::new(color.red, color.green, color.blue, alpha)
+		})
+		.collect();
+	Ok(Image::new(Extent2::new(info.width, info.height), pixels))
+}
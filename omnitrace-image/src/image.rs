@@ -0,0 +1,199 @@
+//! rendered pixels in memory, see [`crate::render`]
+
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::ops::{Index, IndexMut};
+use std::path::Path;
+
+use omnitrace::describe::Node;
+use omnitrace::prelude::*;
+use omnitrace_math::prelude::*;
+use palette::{Srgba, WithAlpha};
+
+use crate::format::{self, Format};
+
+pub(crate) fn to_srgba8(v: Color) -> Srgba<u8> {
+	let (color, alpha): (_, f32) = v.split();
+	palette::Srgb::from_linear(color).with_alpha((alpha * 255.0) as u8)
+}
+
+/// linear [`Color`] pixels, row by row
+#[derive(Debug, Clone, PartialEq)]
+pub struct Image {
+	size: Extent2<u32>,
+	pixels: Vec<Color>,
+}
+impl Image {
+	/// panics if `pixels` doesn't have `size.w * size.h` elements
+	pub fn new(size: Extent2<u32>, pixels: Vec<Color>) -> Self {
+		assert_eq!(pixels.len(), size.w as usize * size.h as usize);
+		Self { size, pixels }
+	}
+	pub fn filled(size: Extent2<u32>, color: Color) -> Self {
+		Self::new(size, vec![color; size.w as usize * size.h as usize])
+	}
+	pub fn size(&self) -> Extent2<u32> {
+		self.size
+	}
+	pub fn pixels(&self) -> &[Color] {
+		&self.pixels
+	}
+	pub fn pixels_mut(&mut self) -> &mut [Color] {
+		&mut self.pixels
+	}
+	pub fn into_pixels(self) -> Vec<Color> {
+		self.pixels
+	}
+	fn index_of(&self, pos: Vec2<u32>) -> Option<usize> {
+		(pos.x < self.size.w && pos.y < self.size.h)
+			.then(|| pos.y as usize * self.size.w as usize + pos.x as usize)
+	}
+	pub fn get(&self, pos: Vec2<u32>) -> Option<Color> {
+		self.index_of(pos).map(|i| self.pixels[i])
+	}
+	pub fn get_mut(&mut self, pos: Vec2<u32>) -> Option<&mut Color> {
+		self.index_of(pos).map(|i| &mut self.pixels[i])
+	}
+	/// the part of `size` starting at `origin` that lies inside the image
+	pub fn crop(&self, origin: Vec2<u32>, size: Extent2<u32>) -> Self {
+		let origin = Vec2::partial_min(origin, Vec2::from(self.size));
+		let size = Extent2::partial_min(size, self.size - Extent2::from(origin));
+		let mut pixels = Vec::with_capacity(size.w as usize * size.h as usize);
+		for y in origin.y..origin.y + size.h {
+			let start = y as usize * self.size.w as usize + origin.x as usize;
+			pixels.extend_from_slice(&self.pixels[start..start + size.w as usize]);
+		}
+		Self::new(size, pixels)
+	}
+	/// tent filter on premultiplied colors, widened when shrinking so small images don't alias
+	pub fn resize(&self, size: Extent2<u32>) -> Self {
+		let pixels = self
+			.pixels
+			.iter()
+			.copied()
+			.map(premultiply)
+			.collect::<Vec<_>>();
+		let pixels = resize_premultiplied(&pixels, self.size, size);
+		Self::new(size, pixels.into_iter().map(unpremultiply).collect())
+	}
+	/// 8-bit sRGB, the way [`Format::Png`] stores it
+	pub fn to_srgba8(&self) -> Vec<Srgba<u8>> {
+		self.pixels.iter().copied().map(to_srgba8).collect()
+	}
+	/// png only, pixels are assumed to be sRGB
+	pub fn decode(r: impl Read) -> io::Result<Self> {
+		format::decode_png(r)
+	}
+	pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+		Self::decode(io::BufReader::new(File::open(path)?))
+	}
+	pub fn encode(&self, w: impl Write, format: Format) -> io::Result<()> {
+		format::encode(self, w, format)
+	}
+	/// the format is picked from the extension
+	pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+		let path = path.as_ref();
+		let format = Format::from_path(path).ok_or_else(|| {
+			io::Error::new(
+				io::ErrorKind::InvalidInput,
+				format!("unknown image format for {path:?}"),
+			)
+		})?;
+		self.save_as(path, format)
+	}
+	pub fn save_as(&self, path: impl AsRef<Path>, format: Format) -> io::Result<()> {
+		let mut file = io::BufWriter::new(File::create(path)?);
+		self.encode(&mut file, format)?;
+		file.flush()
+	}
+}
+
+pub(crate) fn premultiply(v: Color) -> Vec4<f32> {
+	Vec4::new(
+		v.red * v.alpha,
+		v.green * v.alpha,
+		v.blue * v.alpha,
+		v.alpha,
+	)
+}
+
+pub(crate) fn unpremultiply(v: Vec4<f32>) -> Color {
+	match v.w > 0.0 {
+		true => Color::new(v.x / v.w, v.y / v.w, v.z / v.w, v.w),
+		false => Color::NONE,
+	}
+}
+
+pub(crate) fn resize_premultiplied(
+	src: &[Vec4<f32>],
+	size: Extent2<u32>,
+	new_size: Extent2<u32>,
+) -> Vec<Vec4<f32>> {
+	let (w, h) = (size.w as usize, size.h as usize);
+	let rows = resample(src, w, h, new_size.w as usize, 1, w);
+	resample(&rows, h, new_size.w as usize, new_size.h as usize, 1, h)
+}
+
+/// resamples `lines` lines of `len` values to `new_len` values, `stride` apart within a line and `line_stride` between lines
+fn resample(
+	src: &[Vec4<f32>],
+	len: usize,
+	lines: usize,
+	new_len: usize,
+	stride: usize,
+	line_stride: usize,
+) -> Vec<Vec4<f32>> {
+	let mut out = vec![Vec4::zero(); new_len * lines];
+	// nothing to sample from or into, an empty source resizes to transparent pixels
+	if len == 0 || new_len == 0 {
+		return out;
+	}
+	let scale = len as f32 / new_len.max(1) as f32;
+	let radius = scale.max(1.0);
+	for i in 0..new_len {
+		let center = (i as f32 + 0.5) * scale - 0.5;
+		let first = (center - radius).ceil().max(0.0) as usize;
+		let last = ((center + radius).floor() as usize).min(len.saturating_sub(1));
+		let weights = (first..=last)
+			.map(|j| (j, (1.0 - (j as f32 - center).abs() / radius).max(0.0)))
+			.collect::<Vec<_>>();
+		let total = weights.iter().map(|(_, w)| w).sum::<f32>();
+		for line in 0..lines {
+			let mut acc = Vec4::zero();
+			for &(j, w) in &weights {
+				acc += src[line * line_stride + j * stride] * w;
+			}
+			// outputs are stored transposed so the second pass reads rows again
+			out[i * lines + line] = if total > 0.0 { acc / total } else { acc };
+		}
+	}
+	out
+}
+
+impl Index<Vec2<u32>> for Image {
+	type Output = Color;
+	fn index(&self, pos: Vec2<u32>) -> &Color {
+		let i = self.index_of(pos).expect("pixel out of bounds");
+		&self.pixels[i]
+	}
+}
+impl IndexMut<Vec2<u32>> for Image {
+	fn index_mut(&mut self, pos: Vec2<u32>) -> &mut Color {
+		let i = self.index_of(pos).expect("pixel out of bounds");
+		&mut self.pixels[i]
+	}
+}
+
+/// transparent outside of the image
+impl Trace<Vec2<u32>, Color> for Image {
+	type Cache = ();
+	#[cfg_attr(feature = "inline", inline(always))]
+	fn trace(&self, input: Vec2<u32>, _cache: &mut Self::Cache) -> Color {
+		self.get(input).unwrap_or(Color::NONE)
+	}
+}
+impl Describe for Image {
+	fn describe(&self) -> Node {
+		Node::new("Image").param("size", format!("{}x{}", self.size.w, self.size.h))
+	}
+}
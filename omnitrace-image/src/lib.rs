@@ -2,53 +2,930 @@ use std::fs::File;
 use std::io;
 use std::path::Path;
 
+use omnitrace::cancel::{Cancel, Cancelled};
 use omnitrace::prelude::*;
+use omnitrace::pretty::bar::Bar;
+use omnitrace::sink::{Sink, TrySink};
 use omnitrace_math::prelude::*;
-use palette::WithAlpha;
+use sequence::{Delay, FrameSink};
 
 pub mod ext;
+pub mod format;
+pub mod image;
+pub mod path;
+pub mod sequence;
 pub mod shapes;
+pub mod sink;
+pub mod stroke;
+pub mod texture;
+
+pub use format::Format;
+pub use image::Image;
 
 pub mod prelude {
-	pub use crate::ext::{TraceExtCompositing, TraceExtSdf, TraceExtVec2Transform};
-	pub use crate::shapes::Circle;
+	pub use crate::ext::{
+		TraceExtCompositing, TraceExtSdf, TraceExtSdfModifiers, TraceExtSdfOps,
+		TraceExtVec2Transform, Width,
+	};
+	pub use crate::path::{FillRule, Path};
+	pub use crate::shapes::{
+		Arc, Capsule, Circle, Ellipse, Pie, Rect, RegularPolygon, Ring, RoundedRect, Segment, Star,
+		Triangle,
+	};
+	pub use crate::stroke::{Cap, Join, StrokeStyle, TraceExtStroke};
+	pub use crate::texture::{Filter, Texture, WrapMode};
 	pub use omnitrace_math::prelude::*;
 }
 
 parameter_const::define! {
 	/// World to Local
 	pub copy_parameter SCALE: Mat2<f64> = Default::default();
+	/// seconds since the start of the animation, set by [`render_frames`], read it with [`read_param`]
+	pub copy_parameter TIME: f64 = 0.0;
+}
+
+#[cfg_attr(feature = "inline", inline(always))]
+pub fn render<T>(name: impl Into<String>, size: Extent2<u32>, trace: T) -> Image
+where
+	T: Trace<Vec2<u32>, Color> + Sync,
+	T::Cache: Send,
+{
+	render_cancellable(name, size, trace, &Cancel::new()).expect("never cancelled")
+}
+
+pub fn render_cancellable<T>(
+	name: impl Into<String>,
+	size: Extent2<u32>,
+	trace: T,
+	cancel: &Cancel,
+) -> Result<Image, Cancelled>
+where
+	T: Trace<Vec2<u32>, Color> + Sync,
+	T::Cache: Send,
+{
+	let pixels = render_with(name, size, trace, cancel, |v| v, Vec::new())?;
+	Ok(Image::new(size, pixels))
 }
 
-// Replace with render(name, size, trace) -> Image + Image::save(self, path)
-pub fn render<T: Trace<Vec2<u32>, Color>>(
+/// streams the pixels into a png instead of keeping them in memory
+#[cfg_attr(feature = "inline", inline(always))]
+pub fn render_to_file<T>(path: impl AsRef<Path>, size: Extent2<u32>, trace: T) -> io::Result<()>
+where
+	T: Trace<Vec2<u32>, Color> + Sync,
+	T::Cache: Send,
+{
+	render_to_file_cancellable(path, size, trace, &Cancel::new())
+}
+
+/// stops once `cancel` is triggered with an error wrapping [`Cancelled`], the partial file is removed
+pub fn render_to_file_cancellable<T>(
 	path: impl AsRef<Path>,
 	size: Extent2<u32>,
 	trace: T,
-) -> io::Result<()> {
+	cancel: &Cancel,
+) -> io::Result<()>
+where
+	T: Trace<Vec2<u32>, Color> + Sync,
+	T::Cache: Send,
+{
 	let path = path.as_ref();
-	let name = format!("Draw {path:?}");
-	let total_len = size.w as u64 * size.h as u64;
-	let iter = 0..total_len;
-	let width = size.w as u64;
+	let file = io::BufWriter::new(File::create(path)?);
+	let res = sink::PngSink::new(file, size)
+		.and_then(|sink| render_into(format!("Draw {path:?}"), size, trace, cancel, sink)?);
+	if res.is_err() {
+		// the sink and its file are dropped by now
+		let _ = std::fs::remove_file(path);
+	}
+	res
+}
+
+/// feeds the 8-bit sRGB pixels into `sink` row by row, cancelling fails with an error wrapping [`Cancelled`]
+pub fn render_into<T, S>(
+	name: impl Into<String>,
+	size: Extent2<u32>,
+	trace: T,
+	cancel: &Cancel,
+	sink: S,
+) -> io::Result<S::Output>
+where
+	T: Trace<Vec2<u32>, Color> + Sync,
+	T::Cache: Send,
+	S: Sink<palette::Srgba<u8>>,
+{
+	render_with(name, size, trace, cancel, image::to_srgba8, sink).map_err(io::Error::other)
+}
+
+fn render_with<T, O, F, S>(
+	name: impl Into<String>,
+	size: Extent2<u32>,
+	trace: T,
+	cancel: &Cancel,
+	map: F,
+	sink: S,
+) -> Result<S::Output, Cancelled>
+where
+	T: Trace<Vec2<u32>, Color> + Sync,
+	T::Cache: Send,
+	O: Send,
+	F: Fn(Color) -> O + Sync,
+	S: Sink<O>,
+{
+	let total_len = size.w as usize * size.h as usize;
+	let width = size.w as usize;
 	let trace = trace
 		.map_in(|v| Vec2::new((v % width) as u32, (v / width) as u32))
-		.map_out(|v| {
-			let (color, alpha): (_, f32) = v.split();
-			palette::Srgb::from_linear(color).with_alpha((alpha * 255.0) as u8)
-		});
-	#[cfg(feature = "parallel")]
-	let pixels = omnitrace::iterate_parallel_fast(name, iter, total_len, trace);
-	#[cfg(not(feature = "parallel"))]
-	let pixels = omnitrace::iterate_linear(name, iter, trace);
-	let bar = omnitrace::pretty::bar::Bar::new(format!("Save {path:?}"), None);
-	let pixels = palette::cast::into_component_slice(&pixels);
-	let file = io::BufWriter::new(File::create(path)?);
-	let mut encoder = png::Encoder::new(file, size.w, size.h);
-	encoder.set_color(png::ColorType::Rgba);
-	encoder.set_depth(png::BitDepth::Eight);
-	encoder.set_source_gamma(png::ScaledFloat::from_scaled(45455));
-	encoder.write_header()?.write_image_data(&pixels)?;
-	drop(bar);
+		.map_out(map)
+		.cancel_on(cancel);
+	omnitrace::iterate_into(name, 0..total_len, trace, TrySink::new(sink))
+}
+
+/// `(duration * fps).ceil()`, an error if that's no frames or not a number of frames at all
+fn frame_count(fps: f64, duration: f64) -> io::Result<u64> {
+	let frames = (duration * fps).ceil();
+	match fps > 0.0 && frames >= 1.0 && frames.is_finite() {
+		true => Ok(frames as u64),
+		false => Err(io::Error::new(
+			io::ErrorKind::InvalidInput,
+			format!("{duration}s at {fps} fps has no frames"),
+		)),
+	}
+}
+
+/// renders `(duration * fps).ceil()` frames with [`TIME`] set to the time of each frame, `InvalidInput` if that's none,
+/// `pattern` is a path where `{}` is replaced by the zero-padded frame number
+pub fn render_frames<T>(
+	pattern: &str,
+	size: Extent2<u32>,
+	fps: f64,
+	duration: f64,
+	trace: T,
+) -> io::Result<()>
+where
+	T: Trace<Vec2<u32>, Color> + Sync,
+	T::Cache: Send,
+{
+	if !pattern.contains("{}") {
+		return Err(io::Error::new(
+			io::ErrorKind::InvalidInput,
+			format!("frame pattern {pattern:?} is missing {{}}"),
+		));
+	}
+	let frames = frame_count(fps, duration)?;
+	let digits = frames.saturating_sub(1).to_string().len();
+	let bar = Bar::new(format!("Animate {pattern:?}"), Some(frames));
+	for frame in 0..frames {
+		let path = pattern.replace("{}", &format!("{frame:0digits$}"));
+		// workers of the frame start with the time set here
+		let res = TIME.with(frame as f64 / fps, || render_to_file(path, size, &trace));
+		if res.is_err() {
+			drop(bar.finish_on_drop(false));
+			return res;
+		}
+		bar.increment(1);
+	}
 	Ok(())
 }
+
+/// like [`render_frames`] but into a single animated file, e.g. [`sequence::ApngSink`]
+pub fn render_animation<T, S>(
+	mut sink: S,
+	size: Extent2<u32>,
+	fps: f64,
+	duration: f64,
+	trace: T,
+) -> io::Result<()>
+where
+	T: Trace<Vec2<u32>, Color> + Sync,
+	T::Cache: Send,
+	S: FrameSink,
+{
+	let frames = frame_count(fps, duration)?;
+	let delay = Delay::from_fps(fps);
+	let bar = Bar::new("Animate", Some(frames));
+	let res = (|| {
+		sink.start(size, u32::try_from(frames).unwrap_or(u32::MAX))?;
+		for frame in 0..frames {
+			let name = format!("Draw frame {frame}");
+			let image = TIME.with(frame as f64 / fps, || render(name, size, &trace));
+			sink.push(&image, delay)?;
+			bar.increment(1);
+		}
+		sink.finish()
+	})();
+	if res.is_err() {
+		drop(bar.finish_on_drop(false));
+	}
+	res
+}
+
+#[cfg(test)]
+mod tests {
+	use super::prelude::*;
+	use super::*;
+	/// distance of `shape` at `(x, y)` with a fresh cache
+	fn at<T: Trace<Vec2<f64>, f64>>(shape: &T, x: f64, y: f64) -> f64 {
+		shape.trace(Vec2::new(x, y), &mut Default::default())
+	}
+	fn assert_close(a: f64, b: f64) {
+		assert!((a - b).abs() < 1e-9, "{a} != {b}");
+	}
+	fn gradient(size: Extent2<u32>) -> Image {
+		let pixels = (0..size.h)
+			.flat_map(|y| (0..size.w).map(move |x| (x, y)))
+			.map(|(x, y)| Color::new(x as f32 / size.w as f32, y as f32 / size.h as f32, 0.5, 1.0))
+			.collect();
+		Image::new(size, pixels)
+	}
+	#[test]
+	fn image() {
+		let image = gradient(Extent2::new(8, 6));
+		let rendered = render("image", image.size(), &image);
+		assert_eq!(rendered, image);
+		assert_eq!(image.trace(Vec2::new(8, 0), &mut ()), Color::NONE);
+		assert_eq!(image[Vec2::new(3, 2)], image.get(Vec2::new(3, 2)).unwrap());
+		let crop = image.crop(Vec2::new(6, 4), Extent2::new(4, 4));
+		assert_eq!(crop.size(), Extent2::new(2, 2));
+		assert_eq!(crop[Vec2::new(1, 1)], image[Vec2::new(7, 5)]);
+		// a flat image stays flat, a gradient keeps its mean
+		let flat = Image::filled(Extent2::new(7, 5), Color::new(0.25, 0.5, 1.0, 0.5));
+		for v in flat.resize(Extent2::new(3, 11)).pixels() {
+			assert!((v.red - 0.25).abs() < 1e-5 && (v.alpha - 0.5).abs() < 1e-5);
+		}
+		let mean = |image: &Image| {
+			image.pixels().iter().map(|v| v.red).sum::<f32>() / image.pixels().len() as f32
+		};
+		let small = image.resize(Extent2::new(4, 3));
+		assert!((mean(&small) - mean(&image)).abs() < 1e-3);
+		// transparent pixels don't bleed their color
+		let mut holes = Image::filled(Extent2::new(4, 4), Color::new(1.0, 0.0, 0.0, 1.0));
+		holes[Vec2::new(1, 1)] = Color::new(0.0, 1.0, 0.0, 0.0);
+		assert!(holes
+			.resize(Extent2::new(2, 2))
+			.pixels()
+			.iter()
+			.all(|v| v.green == 0.0));
+		// empty images
+		let empty = image.crop(Vec2::new(8, 0), Extent2::new(4, 4));
+		assert_eq!(empty.size(), Extent2::new(0, 4));
+		assert_eq!(empty.resize(Extent2::new(2, 2)).pixels(), [Color::NONE; 4]);
+		assert_eq!(image.resize(Extent2::new(0, 3)).pixels().len(), 0);
+	}
+	#[test]
+	fn frames() {
+		let dir = std::env::temp_dir().join(format!("omnitrace-frames-{}", std::process::id()));
+		std::fs::create_dir_all(&dir).unwrap();
+		let pattern = dir.join("frame-{}.png");
+		let pattern = pattern.to_str().unwrap();
+		let trace = Func(|_: Vec2<u32>| Color::new(TIME.get() as f32, 0.0, 0.0, 1.0));
+		render_frames(pattern, Extent2::new(2, 2), 4.0, 0.6, trace).unwrap();
+		// ceil(0.6 * 4) frames, a quarter second apart
+		for frame in 0..3 {
+			let image = Image::load(pattern.replace("{}", &frame.to_string())).unwrap();
+			let time = frame as f32 / 4.0;
+			assert!((image[Vec2::new(1, 1)].red - time).abs() < 0.01);
+		}
+		assert!(!dir.join("frame-3.png").exists());
+		let missing = render_frames("frame.png", Extent2::new(2, 2), 4.0, 1.0, trace);
+		assert_eq!(missing.unwrap_err().kind(), io::ErrorKind::InvalidInput);
+		for (fps, duration) in [(0.0, 1.0), (-4.0, -1.0), (f64::NAN, 1.0), (4.0, 0.0)] {
+			let none = render_frames(pattern, Extent2::new(2, 2), fps, duration, trace);
+			assert_eq!(none.unwrap_err().kind(), io::ErrorKind::InvalidInput);
+		}
+		std::fs::remove_dir_all(dir).unwrap();
+	}
+	#[test]
+	fn cancel() {
+		let path =
+			std::env::temp_dir().join(format!("omnitrace-cancel-{}.png", std::process::id()));
+		let trace = Func(|_: Vec2<u32>| Color::new(1.0, 0.0, 0.0, 1.0));
+		let cancel = Cancel::new();
+		cancel.cancel();
+		let res = render_cancellable("Cancel", Extent2::new(4, 4), trace, &cancel);
+		assert_eq!(res.unwrap_err(), Cancelled);
+		let err =
+			render_to_file_cancellable(&path, Extent2::new(4, 4), trace, &cancel).unwrap_err();
+		assert_eq!(err.kind(), io::ErrorKind::Other);
+		assert!(err.get_ref().is_some_and(|e| e.is::<Cancelled>()));
+		assert!(!path.exists());
+		render_to_file(&path, Extent2::new(4, 4), trace).unwrap();
+		assert_eq!(Image::load(&path).unwrap().size(), Extent2::new(4, 4));
+		std::fs::remove_file(path).unwrap();
+	}
+	#[test]
+	fn animations() {
+		use sequence::{ApngSink, GifSink};
+		assert_eq!(
+			Delay::from_fps(24.0),
+			Delay {
+				numerator: 1,
+				denominator: 24
+			}
+		);
+		assert_eq!(
+			Delay::from_fps(30000.0 / 1001.0),
+			Delay {
+				numerator: 1001,
+				denominator: 30000
+			}
+		);
+		let find = |data: &[u8], tag: &[u8]| {
+			let at = data.windows(tag.len()).position(|v| v == tag).unwrap();
+			at + tag.len()
+		};
+		let trace = Func(|_: Vec2<u32>| Color::new(TIME.get() as f32, 0.0, 1.0, 1.0));
+		let mut apng = Vec::new();
+		render_animation(
+			ApngSink::new(&mut apng, 0),
+			Extent2::new(3, 2),
+			30.0,
+			0.1,
+			trace,
+		)
+		.unwrap();
+		assert_eq!(apng[..8], *b"\x89PNG\r\n\x1a\n");
+		// frame count & loops, then the delay of the first frame
+		let actl = find(&apng, b"acTL");
+		assert_eq!(apng[actl..actl + 8], [0, 0, 0, 3, 0, 0, 0, 0]);
+		let fctl = find(&apng, b"fcTL") + 20;
+		assert_eq!(apng[fctl..fctl + 4], [0, 1, 0, 30]);
+		assert_eq!(Image::decode(&apng[..]).unwrap().size(), Extent2::new(3, 2));
+		let mut gif = Vec::new();
+		render_animation(
+			GifSink::new(&mut gif, 2),
+			Extent2::new(3, 2),
+			30.0,
+			1.0,
+			trace,
+		)
+		.unwrap();
+		assert_eq!(gif[..6], *b"GIF89a");
+		let delays = gif
+			.windows(6)
+			.filter(|v| v[..3] == [0x21, 0xf9, 0x04])
+			.map(|v| u16::from_le_bytes([v[4], v[5]]))
+			.collect::<Vec<_>>();
+		// centiseconds don't drift over the second
+		assert_eq!(delays.len(), 30);
+		assert!(delays.iter().all(|&v| v == 3 || v == 4));
+		assert_eq!(delays.iter().sum::<u16>(), 100);
+		let empty = render_animation(
+			ApngSink::new(Vec::new(), 0),
+			Extent2::new(3, 2),
+			30.0,
+			0.0,
+			trace,
+		);
+		assert_eq!(empty.unwrap_err().kind(), io::ErrorKind::InvalidInput);
+		assert!(ApngSink::new(Vec::new(), 0)
+			.start(Extent2::new(3, 2), 0)
+			.is_err());
+		let mut sink = GifSink::new(Vec::new(), 0);
+		sink.start(Extent2::new(3, 2), 1).unwrap();
+		let wrong = sink.push(&gradient(Extent2::new(2, 2)), Delay::from_fps(30.0));
+		assert_eq!(wrong.unwrap_err().kind(), io::ErrorKind::InvalidInput);
+		let longest = Delay {
+			numerator: 65535,
+			denominator: 1000,
+		};
+		for fps in [f64::NAN, 0.0, -1.0] {
+			assert_eq!(Delay::from_fps(fps), longest);
+		}
+	}
+	#[test]
+	fn formats() {
+		let image = gradient(Extent2::new(5, 3));
+		let encode = |format| {
+			let mut data = Vec::new();
+			image.encode(&mut data, format).unwrap();
+			data
+		};
+		let close = |a: &Image, tolerance: f32| {
+			a.pixels().iter().zip(image.pixels()).all(|(a, b)| {
+				(a.red - b.red).abs() < tolerance && (a.green - b.green).abs() < tolerance
+			})
+		};
+		let png = encode(Format::Png);
+		assert_eq!(png[..8], *b"\x89PNG\r\n\x1a\n");
+		assert!(close(&Image::decode(&png[..]).unwrap(), 0.01));
+		let png16 = encode(Format::Png16);
+		// bit depth 16, color type rgba
+		assert_eq!(png16[24..26], [16, 6]);
+		assert!(close(&Image::decode(&png16[..]).unwrap(), 1e-4));
+		// rows are stored bottom to top
+		let pfm = encode(Format::Pfm);
+		let header = b"PF\n5 3\n-1.0\n";
+		assert_eq!(pfm[..header.len()], *header);
+		assert_eq!(pfm.len(), header.len() + 5 * 3 * 3 * 4);
+		let first = f32::from_le_bytes(pfm[header.len() + 4..header.len() + 8].try_into().unwrap());
+		assert_eq!(first, image[Vec2::new(0, 2)].green);
+		assert_eq!(encode(Format::Exr)[..4], [0x76, 0x2f, 0x31, 0x01]);
+		let ppm = encode(Format::Ppm);
+		assert!(ppm.starts_with(b"P6\n5 3\n255\n"));
+		assert_eq!(ppm.len(), 11 + 5 * 3 * 3);
+		let pam = encode(Format::Pam);
+		assert!(pam.starts_with(b"P7\nWIDTH 5\nHEIGHT 3\nDEPTH 4\n"));
+		assert!(pam.ends_with(palette::cast::into_component_slice(&image.to_srgba8())));
+		assert_eq!(Format::from_path("a/b.EXR"), Some(Format::Exr));
+		assert_eq!(
+			image.save("image.bmp").unwrap_err().kind(),
+			io::ErrorKind::InvalidInput
+		);
+	}
+	#[test]
+	fn textures() {
+		let values = [0.0, 1.0, 0.25, 0.75];
+		let row = Image::new(
+			Extent2::new(4, 1),
+			values.map(|v| Color::new(v, 0.0, 0.0, 1.0)).to_vec(),
+		);
+		let sample = |texture: &Texture, x: f64| texture.trace(Vec2::new(x, 0.5), &mut ()).red;
+		let texture = Texture::new(&row);
+		let nearest = texture.clone().filter(Filter::Nearest);
+		let bicubic = texture.clone().filter(Filter::Bicubic);
+		for (i, v) in values.into_iter().enumerate() {
+			assert_eq!(sample(&nearest, i as f64 + 0.9), v);
+			assert!((sample(&texture, i as f64 + 0.5) - v).abs() < 1e-6);
+			assert!((sample(&bicubic, i as f64 + 0.5) - v).abs() < 1e-6);
+		}
+		// halfway between texel centers
+		assert!((sample(&texture, 1.0) - 0.5).abs() < 1e-6);
+		let wrapped = |wrap, x| sample(&nearest.clone().wrap(wrap), x);
+		assert_eq!(wrapped(WrapMode::Clamp, -1.5), 0.0);
+		assert_eq!(wrapped(WrapMode::Repeat, -1.5), 0.25);
+		assert_eq!(wrapped(WrapMode::Mirror, -1.5), 1.0);
+		assert_eq!(wrapped(WrapMode::Mirror, 4.5), 0.75);
+		assert_eq!(wrapped(WrapMode::Repeat, 4.5), 0.0);
+		// colors of transparent texels don't bleed
+		let alpha = Image::new(
+			Extent2::new(2, 1),
+			vec![
+				Color::new(1.0, 0.0, 0.0, 1.0),
+				Color::new(0.0, 1.0, 0.0, 0.0),
+			],
+		);
+		let blended = Texture::new(&alpha).trace(Vec2::new(1.0f64, 0.5), &mut ());
+		assert!((blended.red - 1.0).abs() < 1e-6 && blended.green == 0.0);
+		assert!((blended.alpha - 0.5).abs() < 1e-6);
+		// minified by 4 the checker averages out
+		let checker = Image::new(
+			Extent2::new(8, 8),
+			(0..64)
+				.map(|i| ((i % 8 + i / 8) % 2) as f32)
+				.map(|v| Color::new(v, v, v, 1.0))
+				.collect(),
+		);
+		let texture = Texture::new(&checker).wrap(WrapMode::Repeat);
+		let minified = SCALE.with(Mat2::identity() * 4.0, || {
+			texture.trace(Vec2::new(3.0f64, 5.0), &mut ())
+		});
+		assert!((minified.red - 0.5).abs() < 1e-3);
+		let empty = Texture::new(&Image::new(Extent2::new(0, 3), Vec::new()));
+		assert_eq!(empty.trace(Vec2::new(0.5f64, 0.5), &mut ()), Color::NONE);
+	}
+	#[test]
+	fn shapes() {
+		use std::f64::consts::{FRAC_PI_2, FRAC_PI_4};
+		let close = |shape: &dyn Fn(Vec2<f64>) -> f64, x: f64, y: f64, expected: f64| {
+			let d = shape(Vec2::new(x, y));
+			assert!(
+				(d - expected).abs() < 1e-9,
+				"{d} at ({x}, {y}), expected {expected}"
+			);
+		};
+		let circle = |p| Circle(1.0).trace(p, &mut ());
+		close(&circle, 2.0, 0.0, 1.0);
+		close(&circle, 0.0, 0.0, -1.0);
+		let rect = |p| {
+			Rect {
+				half_size: Vec2::new(2.0, 1.0),
+			}
+			.trace(p, &mut ())
+		};
+		close(&rect, 3.0, 0.0, 1.0);
+		close(&rect, 3.0, 2.0, 2f64.sqrt());
+		close(&rect, 0.0, 0.0, -1.0);
+		let rounded = |p| {
+			RoundedRect {
+				half_size: Vec2::new(2.0, 1.0),
+				radius: 0.5,
+			}
+			.trace(p, &mut ())
+		};
+		close(&rounded, 3.0, 0.0, 1.0);
+		close(&rounded, 3.0, 2.0, 4.5f64.sqrt() - 0.5);
+		let (a, b) = (Vec2::new(0.0, 0.0), Vec2::new(2.0, 0.0));
+		let segment = |p| Segment { a, b }.trace(p, &mut ());
+		close(&segment, 1.0, 1.0, 1.0);
+		close(&segment, 3.0, 0.0, 1.0);
+		close(&segment, 1.0, 0.0, 0.0);
+		let capsule = |p| Capsule { a, b, radius: 0.5 }.trace(p, &mut ());
+		close(&capsule, 1.0, 1.0, 0.5);
+		close(&capsule, -1.0, 0.0, 0.5);
+		close(&capsule, 1.0, 0.0, -0.5);
+		let ellipse = |p| {
+			Ellipse {
+				radii: Vec2::new(2.0, 1.0),
+			}
+			.trace(p, &mut ())
+		};
+		close(&ellipse, 3.0, 0.0, 1.0);
+		close(&ellipse, 0.0, 2.0, 1.0);
+		close(&ellipse, 0.0, 0.0, -1.0);
+		let arc = |p| {
+			Arc {
+				radius: 1.0,
+				aperture: FRAC_PI_2,
+				thickness: 0.2,
+			}
+			.trace(p, &mut ())
+		};
+		close(&arc, 0.0, 2.0, 0.9);
+		close(&arc, 0.0, 1.0, -0.1);
+		close(&arc, 0.0, -2.0, 5f64.sqrt() - 0.1);
+		let ring = |p| {
+			Ring {
+				radius: 1.0,
+				thickness: 0.2,
+			}
+			.trace(p, &mut ())
+		};
+		close(&ring, 0.0, 0.0, 0.9);
+		close(&ring, 0.0, -1.0, -0.1);
+		let pie = |p| {
+			Pie {
+				radius: 1.0,
+				aperture: FRAC_PI_4,
+			}
+			.trace(p, &mut ())
+		};
+		close(&pie, 0.0, 2.0, 1.0);
+		close(&pie, 0.0, 0.5, -0.5 * FRAC_PI_4.sin());
+		close(&pie, 0.0, -1.0, 1.0);
+		let triangle = |p| {
+			Triangle {
+				a: Vec2::new(0.0, 0.0),
+				b: Vec2::new(2.0, 0.0),
+				c: Vec2::new(0.0, 2.0),
+			}
+			.trace(p, &mut ())
+		};
+		let reversed = |p| {
+			Triangle {
+				a: Vec2::new(0.0, 2.0),
+				b: Vec2::new(2.0, 0.0),
+				c: Vec2::new(0.0, 0.0),
+			}
+			.trace(p, &mut ())
+		};
+		for triangle in [&triangle as &dyn Fn(_) -> _, &reversed] {
+			close(triangle, -1.0, 0.0, 1.0);
+			close(triangle, 0.5, 0.5, -0.5);
+			close(triangle, 2.0, 2.0, 2f64.sqrt());
+		}
+		// a diamond with its corners on the axes
+		let square = |p| {
+			RegularPolygon {
+				radius: 1.0,
+				sides: 4,
+			}
+			.trace(p, &mut ())
+		};
+		close(&square, 0.0, 0.0, -FRAC_PI_4.cos());
+		close(&square, 0.0, 2.0, 1.0);
+		close(&square, 2.0, 0.0, 1.0);
+		close(&square, 0.5, 0.5, 0.0);
+		let star = |sharpness| {
+			move |p| {
+				Star {
+					radius: 1.0,
+					points: 5,
+					sharpness,
+				}
+				.trace(p, &mut ())
+			}
+		};
+		let pentagon = |p| {
+			RegularPolygon {
+				radius: 1.0,
+				sides: 5,
+			}
+			.trace(p, &mut ())
+		};
+		for p in [(0.0, 0.0), (0.3, 0.7), (-2.0, 0.5), (0.1, -3.0)] {
+			close(&star(2.0), p.0, p.1, pentagon(Vec2::from(p)));
+		}
+		close(&pentagon, 0.0, 0.0, -36f64.to_radians().cos());
+		// a pentagram, its inner corners are at cos(72°) / cos(36°)
+		let pentagram = star(10.0 / 3.0);
+		close(&pentagram, 0.0, 1.0, 0.0);
+		close(&pentagram, 0.0, 2.0, 1.0);
+		close(
+			&pentagram,
+			0.0,
+			0.0,
+			-72f64.to_radians().cos() / 36f64.to_radians().cos(),
+		);
+		// at the sharpest the edges meet in the center, between the tips is outside
+		close(&star(5.0), 0.0, 0.0, 0.0);
+		close(&star(5.0), 0.0, -0.5, 0.5 * 36f64.to_radians().sin());
+	}
+	#[test]
+	fn booleans() {
+		fn assert_at<T: Trace<Vec2<f64>, f64>>(
+			shape: T,
+			points: &[(f64, f64)],
+			expected: [f64; 4],
+		) {
+			for (&p, expected) in points.iter().zip(expected) {
+				let d = at(&shape, p.0, p.1);
+				assert!(
+					(d - expected).abs() < 1e-9,
+					"{d} at {p:?}, expected {expected}"
+				);
+			}
+		}
+		let circle = Circle(1.0);
+		let bar = Rect {
+			half_size: Vec2::new(0.5, 2.0),
+		};
+		// (circle, bar) are (-1, -0.5), (0.5, -0.5), (-0.2, 0.3) and (2, 2.5)
+		let points = [(0.0, 0.0), (0.0, 1.5), (0.8, 0.0), (3.0, 0.0)];
+		let union = [-1.0, -0.5, -0.2, 2.0];
+		let intersect = [-0.5, 0.5, 0.3, 2.5];
+		let subtract = [0.5, 0.5, -0.2, 2.0];
+		let xor = [0.5, -0.5, -0.2, 2.0];
+		assert_at(circle.union(bar), &points, union);
+		assert_at(circle.intersect(bar), &points, intersect);
+		assert_at(circle.subtract(bar), &points, subtract);
+		assert_at(circle.xor(bar), &points, xor);
+		assert_at(circle.smooth_union(bar, 0.0), &points, union);
+		assert_at(circle.smooth_intersect(bar, 0.0), &points, intersect);
+		assert_at(circle.smooth_subtract(bar, 0.0), &points, subtract);
+		assert_at(circle.smooth_xor(bar, 0.0), &points, xor);
+		// sharp where the distances differ by at least k
+		assert_at(circle.smooth_union(bar, 0.4), &points, union);
+		assert_at(circle.smooth_intersect(bar, 0.4), &points, intersect);
+		// both distances are -0.5 at (0, 0.5), blending moves them by k / 4
+		let equal = [(0.0, 0.5)];
+		assert_at(circle.smooth_union(bar, 1.0), &equal, [-0.75; 4]);
+		assert_at(circle.smooth_intersect(bar, 1.0), &equal, [-0.25; 4]);
+		for y in (0..40).map(|v| v as f64 * 0.1 - 2.0) {
+			let smooth = at(&circle.smooth_union(bar, 1.0), 0.3, y);
+			assert!(smooth <= at(&circle.union(bar), 0.3, y));
+			let smooth = at(&circle.smooth_intersect(bar, 1.0), 0.3, y);
+			assert!(smooth >= at(&circle.intersect(bar), 0.3, y));
+		}
+	}
+	#[test]
+	fn modifiers() {
+		use std::cell::Cell;
+		use std::f64::consts::{FRAC_PI_2, PI};
+		fn assert_mat_close(a: Mat2<f64>, b: Mat2<f64>) {
+			let close = (a - b).into_row_array().iter().all(|v| v.abs() < 1e-6);
+			assert!(close, "{a:?} != {b:?}");
+		}
+		let square = Rect {
+			half_size: Vec2::new(1.0, 1.0),
+		};
+		assert_close(at(&square.round(0.5), 2.0, 0.0), 0.5);
+		assert_close(at(&square.round(0.5), 2.0, 2.0), 2f64.sqrt() - 0.5);
+		assert_close(at(&Circle(1.0).onion(0.2), 0.0, 0.0), 0.9);
+		assert_close(at(&Circle(1.0).onion(0.2), 1.0, 0.0), -0.1);
+		let repeated = Circle(1.0).repeat(Vec2::new(4.0, 0.0));
+		assert_close(at(&repeated, 8.5, 0.0), -0.5);
+		assert_close(at(&repeated, -6.0, 0.0), 1.0);
+		assert_close(at(&repeated, 8.0, 3.0), 2.0);
+		let limited = Circle(1.0).repeat_limited(Vec2::new(4.0, 4.0), Vec2::new(1.0, 1.0));
+		assert_close(at(&limited, 4.0, -4.0), -1.0);
+		assert_close(at(&limited, 9.0, 0.0), 4.0);
+		let moved = Circle(1.0).trans(Mat3::translation_2d(Vec2::new(2.0, 0.0)));
+		assert_close(at(&moved.mirror_x(), -2.0, 0.0), -1.0);
+		assert_close(at(&moved.mirror_y(), -2.0, 0.0), 3.0);
+		let polar = moved.repeat_polar(4);
+		for (x, y) in [(2.0, 0.0), (0.0, 2.0), (-2.0, 0.0), (0.0, -2.0)] {
+			assert_close(at(&polar, x, y), -1.0);
+		}
+		assert_close(at(&polar, 0.0, 0.0), 1.0);
+		// round things stay round
+		for (x, y) in [(0.3, 0.0), (1.0, -2.0), (-0.5, 0.7)] {
+			assert_close(at(&Circle(1.0).twist(2.0), x, y), at(&Circle(1.0), x, y));
+			assert_close(at(&square.twist(0.0), x, y), at(&square, x, y));
+		}
+		// (0, 1) is turned a quarter onto the tip of a bar along x
+		let bar = Rect {
+			half_size: Vec2::new(1.0, 0.1),
+		};
+		assert_close(at(&bar.twist(FRAC_PI_2), 0.0, 1.0), 0.0);
+		assert!(at(&bar.twist(FRAC_PI_2), 1.0, 0.0) > 0.5);
+		// `SCALE` is composed like `Trans` does
+		let seen = Cell::new((Mat2::identity(), Vec2::zero()));
+		let probe = Func(|p: Vec2<f64>| {
+			seen.set((SCALE.get(), p));
+			0.0
+		});
+		let shear = Mat2::new(1.0, 2.0, 0.0, 1.0);
+		SCALE.with(shear, || {
+			at(&probe.mirror_x(), -1.0, 0.5);
+			assert_mat_close(seen.get().0, shear * Mat2::scaling_2d(Vec2::new(-1.0, 1.0)));
+			at(&probe.repeat_polar(4), 0.0, 2.0);
+			assert_mat_close(seen.get().0, shear * Mat2::rotation_z(-FRAC_PI_2));
+			at(&probe.repeat(Vec2::new(3.0, 3.0)), 5.0, 5.0);
+			assert_mat_close(seen.get().0, shear);
+		});
+		// the twist's jacobian matches finite differences
+		let twisted = probe.twist(PI / 3.0);
+		let local = |x, y| {
+			at(&twisted, x, y);
+			seen.get().1
+		};
+		for (x, y) in [(1.0, 0.5), (-2.0, 0.3), (0.2, -0.1)] {
+			let h = 1e-6;
+			let dx = (local(x + h, y) - local(x - h, y)) / (2.0 * h);
+			let dy = (local(x, y + h) - local(x, y - h)) / (2.0 * h);
+			let numeric = Mat2::new(dx.x, dy.x, dx.y, dy.y);
+			SCALE.with(shear, || at(&twisted, x, y));
+			assert_mat_close(seen.get().0, shear * numeric);
+		}
+	}
+	#[test]
+	fn paths() {
+		use crate::path::Path;
+		let square = |min: f64, size: f64| {
+			[(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)]
+				.map(|(x, y)| Vec2::new(x, y) * size + min)
+		};
+		let polygon = Path::polygon(square(0.0, 4.0));
+		assert_eq!(at(&polygon, 1.0, 2.0), -1.0);
+		assert_eq!(at(&polygon, 6.0, 2.0), 2.0);
+		assert_eq!(at(&polygon.clone().unsigned(), 1.0, 2.0), 1.0);
+		// open subpaths are closed for filling but not for the outline
+		let open = Path::new()
+			.move_to(Vec2::new(0.0, 0.0))
+			.line_to(Vec2::new(4.0, 0.0))
+			.line_to(Vec2::new(4.0, 4.0));
+		assert_eq!(at(&open, 3.0, 1.0), -1.0);
+		assert!((at(&open, 1.0, 3.0) - 2f64.sqrt()).abs() < 1e-9);
+		assert_eq!(at(&open.unsigned(), 1.0, 3.0), 3.0);
+		// a pentagram drawn in one go overlaps itself in the middle
+		let tips = (0..5).map(|i| {
+			let angle = (i * 2 % 5) as f64 * std::f64::consts::TAU / 5.0;
+			Vec2::new(angle.sin(), angle.cos())
+		});
+		let pentagram = Path::polygon(tips);
+		let inner = 72f64.to_radians().cos();
+		assert!((at(&pentagram, 0.0, 0.0) + inner).abs() < 1e-9);
+		let even_odd = pentagram.clone().fill_rule(FillRule::EvenOdd);
+		assert!((at(&even_odd, 0.0, 0.0) - inner).abs() < 1e-9);
+		// a tip is inside with either rule
+		assert!(at(&pentagram, 0.0, 0.9) < 0.0 && at(&even_odd, 0.0, 0.9) < 0.0);
+		// overlapping squares, a reversed one cuts a hole for both rules
+		let squares = |second: [Vec2<f64>; 4]| {
+			let path = Path::polygon(square(0.0, 4.0));
+			second[1..]
+				.iter()
+				.fold(path.move_to(second[0]), |path, &p| path.line_to(p))
+				.close()
+		};
+		let same = squares(square(2.0, 4.0));
+		let mut reversed = square(2.0, 4.0);
+		reversed.reverse();
+		let reversed = squares(reversed);
+		assert_eq!(at(&same, 3.0, 3.0), -1.0);
+		assert_eq!(
+			at(&same.clone().fill_rule(FillRule::EvenOdd), 3.0, 3.0),
+			1.0
+		);
+		assert_eq!(at(&reversed, 3.0, 3.0), 1.0);
+		assert_eq!(at(&reversed.fill_rule(FillRule::EvenOdd), 3.0, 3.0), 1.0);
+		assert_eq!(at(&same, 5.0, 5.0), -1.0);
+		// flattened curves stay within the tolerance and get more points for a smaller one
+		let (start, c1, c2, end) = (
+			Vec2::new(0.0, 0.0),
+			Vec2::new(1.0, 3.0),
+			Vec2::new(3.0, -2.0),
+			Vec2::new(4.0, 1.0),
+		);
+		let mut counts = Vec::new();
+		for tolerance in [0.1, 0.01, 0.001] {
+			let quad = Path::new()
+				.tolerance(tolerance)
+				.move_to(start)
+				.quad_to(c1, end)
+				.unsigned();
+			let cubic = Path::new()
+				.tolerance(tolerance)
+				.move_to(start)
+				.cubic_to(c1, c2, end)
+				.unsigned();
+			for t in (0..=100).map(|v| v as f64 / 100.0) {
+				let u = 1.0 - t;
+				let p = start * u * u + c1 * 2.0 * u * t + end * t * t;
+				assert!(at(&quad, p.x, p.y) <= tolerance);
+				let p = start * u * u * u
+					+ c1 * 3.0 * u * u * t
+					+ c2 * 3.0 * u * t * t
+					+ end * t * t * t;
+				assert!(at(&cubic, p.x, p.y) <= tolerance);
+			}
+			counts.push(cubic.subpaths[0].points.len());
+		}
+		assert!(counts.windows(2).all(|v| v[0] < v[1]));
+		// a trailing `move_to` draws nothing, a zero length line does
+		let round = StrokeStyle::new(1.0).cap(Cap::Round);
+		let dangling = Path::polygon(square(0.0, 4.0)).move_to(Vec2::new(10.0, 10.0));
+		assert_eq!(at(&dangling, 10.0, 10.0), 72f64.sqrt());
+		assert!(at(&dangling.stroke(round.clone()), 10.0, 10.0) > 5.0);
+		let dot = Path::new()
+			.move_to(Vec2::new(10.0, 10.0))
+			.line_to(Vec2::new(10.0, 10.0));
+		assert_eq!(at(&dot.stroke(round.clone()), 10.0, 10.0), -0.5);
+		let closed = Path::new().move_to(Vec2::new(10.0, 10.0)).close();
+		assert_eq!(at(&closed.stroke(round), 10.0, 10.0), -0.5);
+	}
+	#[test]
+	fn strokes() {
+		use crate::path::Path;
+		// any distance field, sized in units or pixels
+		assert_close(at(&Circle(1.0).stroke(Width::Units(0.2)), 1.0, 0.0), -0.1);
+		assert_close(at(&Circle(1.0).stroke(Width::Units(0.2)), 0.0, 0.0), 0.9);
+		let pixels = Circle(1.0).stroke(Width::Pixels(2.0));
+		SCALE.with(Mat2::identity() * 0.5, || {
+			assert_close(at(&pixels, 1.0, 0.0), -0.5);
+			assert_close(at(&Circle(1.0).onion(Width::Pixels(2.0)), 1.5, 0.0), 0.0);
+		});
+		// an L turning left at (4, 0), the outside of the corner points to (5, -1)
+		let corner = Path::new()
+			.move_to(Vec2::new(0.0, 0.0))
+			.line_to(Vec2::new(4.0, 0.0))
+			.line_to(Vec2::new(4.0, 4.0));
+		let style = StrokeStyle::new(2.0);
+		let stroke = |style: StrokeStyle<f64>| corner.clone().stroke(style);
+		let miter = stroke(style.clone().join(Join::Miter));
+		assert_close(at(&miter, 5.0, -1.0), 0.0);
+		assert!(at(&miter, 4.9, -0.9) < 0.0);
+		let bevel = 0.5f64.sqrt();
+		assert_close(
+			at(&stroke(style.clone().join(Join::Bevel)), 5.0, -1.0),
+			bevel,
+		);
+		assert_close(
+			at(&stroke(style.clone().join(Join::Round)), 5.0, -1.0),
+			2f64.sqrt() - 1.0,
+		);
+		// the miter is sqrt(2) times the width
+		let limited = style.clone().join(Join::Miter).miter_limit(1.4);
+		assert_close(at(&stroke(limited), 5.0, -1.0), bevel);
+		// the start at (0, 0) faces -x
+		let butt = stroke(style.clone());
+		assert_close(at(&butt, -0.5, 0.0), 0.5);
+		assert_close(at(&butt, 1.0, 0.0), -1.0);
+		let square = stroke(style.clone().cap(Cap::Square));
+		assert_close(at(&square, -0.5, 0.0), -0.5);
+		assert_close(at(&square, -1.5, 0.0), 0.5);
+		assert_close(at(&square, -1.0, 1.0), 0.0);
+		let round = stroke(style.clone().cap(Cap::Round));
+		assert_close(at(&round, -0.5, 0.0), -0.5);
+		assert_close(at(&round, -1.0, 1.0), 2f64.sqrt() - 1.0);
+		SCALE.with(Mat2::identity() * 3.0, || {
+			let wide = stroke(StrokeStyle::pixels(2.0));
+			assert_close(at(&wide, 2.0, 2.0), -1.0);
+			assert_close(at(&wide, 2.0, 6.0), 2.0);
+		});
+		// dashes of 2 and gaps of 1 along x, shifted by the offset
+		let line = Path::new()
+			.move_to(Vec2::new(0.0, 0.0))
+			.line_to(Vec2::new(10.0, 0.0));
+		let dashed = |offset| line.clone().stroke(style.clone().dash([2.0, 1.0], offset));
+		let gaps = |offset, gaps: &[f64]| {
+			let dashed = dashed(offset);
+			for x in (0..20).map(|v| v as f64 * 0.5 + 0.25) {
+				let gap = gaps.iter().any(|&g| x > g && x < g + 1.0);
+				assert_eq!(
+					at(&dashed, x, 0.0) > 0.0,
+					gap,
+					"{x} with an offset of {offset}"
+				);
+			}
+		};
+		gaps(0.0, &[2.0, 5.0, 8.0]);
+		gaps(1.0, &[1.0, 4.0, 7.0]);
+		gaps(-1.0, &[0.0, 3.0, 6.0, 9.0]);
+		gaps(4.0, &[1.0, 4.0, 7.0]);
+		assert_close(at(&dashed(0.0), 2.5, 0.0), 0.5);
+		assert_close(at(&dashed(0.0), 1.0, 0.0), -1.0);
+		// an odd pattern is repeated, [3] is [3, 3]
+		let odd = line.clone().stroke(style.clone().dash([3.0], 0.0));
+		assert!(at(&odd, 4.5, 0.0) > 0.0 && at(&odd, 7.5, 0.0) < 0.0);
+		// on a closed square the dash over the start is one piece, so the corner is joined
+		let outline = Path::polygon(
+			[(0.0, 0.0), (4.0, 0.0), (4.0, 4.0), (0.0, 4.0)].map(|(x, y)| Vec2::new(x, y)),
+		);
+		let dashed = outline.stroke(style.clone().dash([3.0, 1.0], 2.0));
+		assert!(at(&dashed, -0.9, -0.9) < 0.0);
+		// the seams between the sides and the miter are inside too
+		assert_close(at(&dashed, -0.5, 0.0), -0.5);
+		assert_close(at(&dashed, -0.5, -0.5), -0.5);
+		assert!(at(&dashed, -0.9, -0.9) < -0.05);
+		assert!(at(&dashed, 1.5, 0.0) > 0.0);
+	}
+}
@@ -0,0 +1,220 @@
+//! vector outlines built from lines and bezier curves
+
+use std::fmt;
+
+use omnitrace::describe::Node;
+use omnitrace::prelude::*;
+use omnitrace_math::prelude::*;
+
+/// which points count as inside when subpaths overlap
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FillRule {
+	#[default]
+	NonZero,
+	EvenOdd,
+}
+
+/// a flattened subpath
+#[derive(Debug, Clone)]
+pub(crate) struct Subpath<V> {
+	pub(crate) points: Vec<Vec2<V>>,
+	pub(crate) closed: bool,
+	/// a line, curve or `close` was added, a lone `move_to` draws nothing
+	pub(crate) drawn: bool,
+}
+
+/// curves are flattened into lines as they're added, see [`Self::tolerance`].
+/// traced as a signed distance, open subpaths are closed for filling like svg does
+#[derive(Debug, Clone)]
+pub struct Path<V> {
+	pub(crate) subpaths: Vec<Subpath<V>>,
+	tolerance: V,
+	rule: Option<FillRule>,
+}
+impl<V: Real> Default for Path<V> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+impl<V: Real> Path<V> {
+	pub fn new() -> Self {
+		Self {
+			subpaths: Vec::new(),
+			tolerance: V::from(0.01).unwrap(),
+			rule: Some(FillRule::default()),
+		}
+	}
+	/// a closed path through `points`
+	pub fn polygon(points: impl IntoIterator<Item = Vec2<V>>) -> Self {
+		let mut points = points.into_iter();
+		let path = match points.next() {
+			Some(first) => Self::new().move_to(first),
+			None => return Self::new(),
+		};
+		points.fold(path, Self::line_to).close()
+	}
+	/// how far flattened curves may stray from the real ones, only affects curves added afterwards
+	pub fn tolerance(self, tolerance: V) -> Self {
+		Self { tolerance, ..self }
+	}
+	pub fn fill_rule(self, rule: FillRule) -> Self {
+		Self {
+			rule: Some(rule),
+			..self
+		}
+	}
+	/// the distance to the outline itself, never negative, open subpaths stay open
+	pub fn unsigned(self) -> Self {
+		Self { rule: None, ..self }
+	}
+
+	fn current(&mut self) -> &mut Subpath<V> {
+		// after `close` the next subpath starts where the last one did
+		let start = match self.subpaths.last() {
+			Some(last) if !last.closed => None,
+			Some(last) => Some(last.points[0]),
+			None => Some(Vec2::zero()),
+		};
+		if let Some(start) = start {
+			self.subpaths.push(Subpath {
+				points: vec![start],
+				closed: false,
+				drawn: false,
+			});
+		}
+		let current = self.subpaths.last_mut().unwrap();
+		current.drawn = true;
+		current
+	}
+	fn last_point(&mut self) -> Vec2<V> {
+		*self.current().points.last().unwrap()
+	}
+	pub fn move_to(mut self, p: Vec2<V>) -> Self {
+		match self.subpaths.last_mut() {
+			// a lone point is replaced instead of leaving a dot behind
+			Some(last) if !last.drawn => last.points[0] = p,
+			_ => self.subpaths.push(Subpath {
+				points: vec![p],
+				closed: false,
+				drawn: false,
+			}),
+		}
+		self
+	}
+	pub fn line_to(mut self, p: Vec2<V>) -> Self {
+		self.current().points.push(p);
+		self
+	}
+	/// quadratic bezier with control point `c`
+	pub fn quad_to(mut self, c: Vec2<V>, p: Vec2<V>) -> Self {
+		let start = self.last_point();
+		let d = (start - c * (V::one() + V::one()) + p).magnitude();
+		let n = self.segments(d / V::from(4.0).unwrap());
+		let points = &mut self.current().points;
+		for i in 1..=n {
+			let t = V::from(i).unwrap() / V::from(n).unwrap();
+			let u = V::one() - t;
+			points.push(start * (u * u) + c * (u * t * (V::one() + V::one())) + p * (t * t));
+		}
+		self
+	}
+	/// cubic bezier with control points `c1` and `c2`
+	pub fn cubic_to(mut self, c1: Vec2<V>, c2: Vec2<V>, p: Vec2<V>) -> Self {
+		let start = self.last_point();
+		let two = V::one() + V::one();
+		let three = two + V::one();
+		let d = (start - c1 * two + c2)
+			.magnitude()
+			.max((c1 - c2 * two + p).magnitude());
+		let n = self.segments(d * V::from(0.75).unwrap());
+		let points = &mut self.current().points;
+		for i in 1..=n {
+			let t = V::from(i).unwrap() / V::from(n).unwrap();
+			let u = V::one() - t;
+			points.push(
+				start * (u * u * u)
+					+ c1 * (three * u * u * t)
+					+ c2 * (three * u * t * t)
+					+ p * (t * t * t),
+			);
+		}
+		self
+	}
+	/// connects back to the start of the subpath
+	pub fn close(mut self) -> Self {
+		self.current().closed = true;
+		self
+	}
+
+	/// lines needed to keep a curve with second differences of `d` within the tolerance
+	fn segments(&self, d: V) -> usize {
+		let n = (d / self.tolerance.max(V::epsilon())).sqrt().ceil();
+		n.to_usize().unwrap_or(1).clamp(1, 1024)
+	}
+
+	/// every line, `(start, end)`, including the closing ones when `fill` is set
+	pub(crate) fn edges(&self, fill: bool) -> impl Iterator<Item = (Vec2<V>, Vec2<V>)> + '_ {
+		self.subpaths
+			.iter()
+			.filter(|v| v.drawn)
+			.flat_map(move |sub| {
+				let closing =
+					(sub.closed || fill).then(|| (*sub.points.last().unwrap(), sub.points[0]));
+				sub.points.windows(2).map(|w| (w[0], w[1])).chain(closing)
+			})
+	}
+}
+
+pub(crate) fn segment_distance_squared<V: Real>(p: Vec2<V>, a: Vec2<V>, b: Vec2<V>) -> V {
+	let (pa, ba) = (p - a, b - a);
+	let len = ba.dot(ba);
+	let h = match len > V::zero() {
+		true => (pa.dot(ba) / len).max(V::zero()).min(V::one()),
+		false => V::zero(),
+	};
+	(pa - ba * h).magnitude_squared()
+}
+
+impl<V: Real> Trace<Vec2<V>, V> for Path<V> {
+	type Cache = ();
+	fn trace(&self, input: Vec2<V>, _cache: &mut Self::Cache) -> V {
+		let fill = self.rule.is_some();
+		let mut dist = V::max_value();
+		let mut winding = 0i32;
+		for (a, b) in self.edges(fill) {
+			dist = dist.min(segment_distance_squared(input, a, b));
+			if fill {
+				let cross = (b.x - a.x) * (input.y - a.y) - (b.y - a.y) * (input.x - a.x);
+				if a.y <= input.y && input.y < b.y && cross > V::zero() {
+					winding += 1;
+				} else if b.y <= input.y && input.y < a.y && cross < V::zero() {
+					winding -= 1;
+				}
+			}
+		}
+		let inside = match self.rule {
+			Some(FillRule::NonZero) => winding != 0,
+			Some(FillRule::EvenOdd) => winding % 2 != 0,
+			None => false,
+		};
+		match inside {
+			true => -dist.sqrt(),
+			false => dist.sqrt(),
+		}
+	}
+}
+impl<V: fmt::Debug> Describe for Path<V> {
+	fn describe(&self) -> Node {
+		let mode = match self.rule {
+			Some(rule) => format!("{rule:?}"),
+			None => "Unsigned".to_string(),
+		};
+		Node::new("Path")
+			.param("subpaths", self.subpaths.len())
+			.param(
+				"points",
+				self.subpaths.iter().map(|v| v.points.len()).sum::<usize>(),
+			)
+			.param("mode", mode)
+	}
+}
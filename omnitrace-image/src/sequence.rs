@@ -0,0 +1,226 @@
+//! animated outputs, see [`crate::render_animation`]
+
+use std::io::{self, Write};
+use std::time::Duration;
+
+use omnitrace_math::prelude::*;
+
+use crate::Image;
+
+/// how long a frame is shown, `numerator / denominator` seconds like apng stores it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Delay {
+	pub numerator: u16,
+	pub denominator: u16,
+}
+impl Delay {
+	/// exact for integer and the usual fractional rates like `30000.0 / 1001.0`,
+	/// a rate that isn't positive, NaN included, gets the longest delay
+	pub fn from_fps(fps: f64) -> Self {
+		let target = match fps > 0.0 {
+			true => 1.0 / fps,
+			false => f64::INFINITY,
+		};
+		let mut best = Self::from(Duration::from_secs_f64(target.clamp(0.0, 65.535)));
+		// convergents of the continued fraction of `1 / fps` until they leave u16
+		let (mut h, mut k) = ((1u64, 0u64), (0u64, 1u64));
+		let mut x = target;
+		while x.is_finite() && x >= 0.0 && x < u16::MAX as f64 {
+			let a = x.floor();
+			h = (a as u64 * h.0 + h.1, h.0);
+			k = (a as u64 * k.0 + k.1, k.0);
+			let (Ok(numerator), Ok(denominator)) = (u16::try_from(h.0), u16::try_from(k.0)) else {
+				break;
+			};
+			if denominator > 0 {
+				best = Self {
+					numerator,
+					denominator,
+				};
+			}
+			if (best.as_secs_f64() - target).abs() <= target * 1e-12 {
+				break;
+			}
+			x = 1.0 / (x - a);
+		}
+		best
+	}
+	pub fn as_secs_f64(self) -> f64 {
+		self.numerator as f64 / self.denominator.max(1) as f64
+	}
+}
+/// milliseconds, longer delays lose precision instead of overflowing
+impl From<Duration> for Delay {
+	fn from(delay: Duration) -> Self {
+		match u16::try_from(delay.as_millis()) {
+			Ok(ms) => Self {
+				numerator: ms,
+				denominator: 1000,
+			},
+			Err(_) => Self {
+				numerator: delay.as_secs().min(u16::MAX as u64) as u16,
+				denominator: 1,
+			},
+		}
+	}
+}
+
+/// receives the frames of an animation in order
+pub trait FrameSink {
+	/// called once before the first frame, `frames` is at least 1
+	fn start(&mut self, size: Extent2<u32>, frames: u32) -> io::Result<()>;
+	/// `delay` is how long the frame is shown
+	fn push(&mut self, frame: &Image, delay: Delay) -> io::Result<()>;
+	fn finish(self) -> io::Result<()>;
+}
+
+fn no_frames() -> io::Error {
+	io::Error::new(
+		io::ErrorKind::InvalidInput,
+		"an animation needs at least one frame",
+	)
+}
+
+enum State<W, E> {
+	New(W),
+	Started(E),
+	Poisoned,
+}
+impl<W, E> State<W, E> {
+	fn start(&mut self, f: impl FnOnce(W) -> io::Result<E>) -> io::Result<()> {
+		match std::mem::replace(self, Self::Poisoned) {
+			Self::New(w) => {
+				*self = Self::Started(f(w)?);
+				Ok(())
+			}
+			_ => Err(io::Error::other("sequence already started")),
+		}
+	}
+	fn started(&mut self) -> io::Result<&mut E> {
+		match self {
+			Self::Started(e) => Ok(e),
+			_ => Err(io::Error::other("sequence not started")),
+		}
+	}
+}
+
+/// animated png, the frame count has to be known up front so every frame must be pushed
+pub struct ApngSink<W: Write> {
+	state: State<W, png::Writer<W>>,
+	loops: u32,
+}
+impl<W: Write> ApngSink<W> {
+	/// `loops == 0` repeats forever
+	pub fn new(w: W, loops: u32) -> Self {
+		Self {
+			state: State::New(w),
+			loops,
+		}
+	}
+}
+impl<W: Write> FrameSink for ApngSink<W> {
+	fn start(&mut self, size: Extent2<u32>, frames: u32) -> io::Result<()> {
+		if frames == 0 {
+			return Err(no_frames());
+		}
+		let loops = self.loops;
+		self.state.start(|w| {
+			let mut encoder = png::Encoder::new(w, size.w, size.h);
+			encoder.set_color(png::ColorType::Rgba);
+			encoder.set_depth(png::BitDepth::Eight);
+			encoder.set_source_gamma(png::ScaledFloat::from_scaled(45455));
+			encoder.set_animated(frames, loops)?;
+			Ok(encoder.write_header()?)
+		})
+	}
+	fn push(&mut self, frame: &Image, delay: Delay) -> io::Result<()> {
+		let writer = self.state.started()?;
+		writer.set_frame_delay(delay.numerator, delay.denominator)?;
+		writer.write_image_data(palette::cast::into_component_slice(&frame.to_srgba8()))?;
+		Ok(())
+	}
+	fn finish(self) -> io::Result<()> {
+		match self.state {
+			State::Started(writer) => Ok(writer.finish()?),
+			_ => Err(io::Error::other("sequence not started")),
+		}
+	}
+}
+
+/// gif with a palette quantized per frame, only fully transparent pixels stay transparent.
+/// gif delays are whole centiseconds, the rounding is spread over the frames so the total doesn't drift
+pub struct GifSink<W: Write> {
+	state: State<W, gif::Encoder<W>>,
+	size: Extent2<u16>,
+	loops: u16,
+	/// exact seconds and written centiseconds of the frames so far
+	elapsed: (f64, u64),
+	/// 1 is the slowest & best quantization, 30 the fastest
+	pub speed: i32,
+}
+impl<W: Write> GifSink<W> {
+	/// `loops == 0` repeats forever
+	pub fn new(w: W, loops: u16) -> Self {
+		Self {
+			state: State::New(w),
+			size: Extent2::new(0, 0),
+			loops,
+			elapsed: (0.0, 0),
+			speed: 10,
+		}
+	}
+}
+impl<W: Write> FrameSink for GifSink<W> {
+	fn start(&mut self, size: Extent2<u32>, frames: u32) -> io::Result<()> {
+		if frames == 0 {
+			return Err(no_frames());
+		}
+		let too_large = || {
+			io::Error::new(
+				io::ErrorKind::InvalidInput,
+				"gif frames are at most 65535 pixels wide",
+			)
+		};
+		self.size = Extent2::new(
+			u16::try_from(size.w).map_err(|_| too_large())?,
+			u16::try_from(size.h).map_err(|_| too_large())?,
+		);
+		let (size, loops) = (self.size, self.loops);
+		self.state.start(|w| {
+			let mut encoder =
+				gif::Encoder::new(w, size.w, size.h, &[]).map_err(io::Error::other)?;
+			let repeat = match loops {
+				0 => gif::Repeat::Infinite,
+				n => gif::Repeat::Finite(n),
+			};
+			encoder.set_repeat(repeat).map_err(io::Error::other)?;
+			Ok(encoder)
+		})
+	}
+	fn push(&mut self, frame: &Image, delay: Delay) -> io::Result<()> {
+		let (size, speed) = (self.size, self.speed);
+		let encoder = self.state.started()?;
+		if frame.size() != size.as_() {
+			return Err(io::Error::new(
+				io::ErrorKind::InvalidInput,
+				"frame size differs from the size of the sequence",
+			));
+		}
+		let (seconds, written) = &mut self.elapsed;
+		*seconds += delay.as_secs_f64();
+		let end = (*seconds * 100.0).round() as u64;
+		let centis = end.saturating_sub(*written).min(u16::MAX as u64);
+		*written += centis;
+		let mut pixels = palette::cast::into_component_slice(&frame.to_srgba8()).to_vec();
+		let mut frame =
+			gif::Frame::from_rgba_speed(size.w, size.h, &mut pixels, speed.clamp(1, 30));
+		frame.delay = centis as u16;
+		encoder.write_frame(&frame).map_err(io::Error::other)
+	}
+	fn finish(self) -> io::Result<()> {
+		match self.state {
+			State::Started(encoder) => encoder.into_inner().map(drop),
+			_ => Err(io::Error::other("sequence not started")),
+		}
+	}
+}
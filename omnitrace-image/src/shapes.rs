@@ -1,6 +1,30 @@
+//! exact signed distances, negative inside, most of these follow <https://iquilezles.org/articles/distfunctions2d/>
+
+use std::fmt;
+
+use omnitrace::describe::Node;
 use omnitrace::prelude::*;
 use omnitrace_math::prelude::*;
 
+fn c<T: Real>(v: f64) -> T {
+	T::from(v).unwrap()
+}
+
+/// glsl `mod`, the result has the sign of `y`
+fn modulo<T: Real>(x: T, y: T) -> T {
+	x - y * (x / y).floor()
+}
+
+macro_rules! impl_describe {
+	($($struct:ident $name:literal { $($field:ident)* })*) => {$(
+		impl<T: fmt::Debug> Describe for $struct<T> {
+			fn describe(&self) -> Node {
+				Node::new($name)$(.param(stringify!($field), format!("{:?}", self.$field)))*
+			}
+		}
+	)*};
+}
+
 #[derive(Debug, Clone, Copy)]
 #[repr(transparent)]
 pub struct Circle<T>(pub T);
@@ -10,3 +34,272 @@ impl<T: Real> Trace<Vec2<T>, T> for Circle<T> {
 		input.magnitude() - self.0
 	}
 }
+impl<T: fmt::Debug> Describe for Circle<T> {
+	fn describe(&self) -> Node {
+		Node::new("Circle").param("radius", format!("{:?}", self.0))
+	}
+}
+
+/// axis aligned, centered on the origin
+#[derive(Debug, Clone, Copy)]
+pub struct Rect<T> {
+	pub half_size: Vec2<T>,
+}
+impl<T: Real> Trace<Vec2<T>, T> for Rect<T> {
+	type Cache = ();
+	fn trace(&self, input: Vec2<T>, _cache: &mut Self::Cache) -> T {
+		let d = input.map(T::abs) - self.half_size;
+		d.map(|v| v.max(T::zero())).magnitude() + d.x.max(d.y).min(T::zero())
+	}
+}
+
+/// a [`Rect`] with circular corners, `half_size` includes the corners
+#[derive(Debug, Clone, Copy)]
+pub struct RoundedRect<T> {
+	pub half_size: Vec2<T>,
+	pub radius: T,
+}
+impl<T: Real> Trace<Vec2<T>, T> for RoundedRect<T> {
+	type Cache = ();
+	fn trace(&self, input: Vec2<T>, cache: &mut Self::Cache) -> T {
+		let radius = self.radius.min(self.half_size.x).min(self.half_size.y);
+		let inner = Rect {
+			half_size: self.half_size - radius,
+		};
+		inner.trace(input, cache) - radius
+	}
+}
+
+/// zero width, so the distance is never negative
+#[derive(Debug, Clone, Copy)]
+pub struct Segment<T> {
+	pub a: Vec2<T>,
+	pub b: Vec2<T>,
+}
+impl<T: Real> Trace<Vec2<T>, T> for Segment<T> {
+	type Cache = ();
+	fn trace(&self, input: Vec2<T>, _cache: &mut Self::Cache) -> T {
+		let (pa, ba) = (input - self.a, self.b - self.a);
+		let len = ba.dot(ba);
+		let h = match len > T::zero() {
+			true => (pa.dot(ba) / len).max(T::zero()).min(T::one()),
+			false => T::zero(),
+		};
+		(pa - ba * h).magnitude()
+	}
+}
+
+/// a [`Segment`] with round caps
+#[derive(Debug, Clone, Copy)]
+pub struct Capsule<T> {
+	pub a: Vec2<T>,
+	pub b: Vec2<T>,
+	pub radius: T,
+}
+impl<T: Real> Trace<Vec2<T>, T> for Capsule<T> {
+	type Cache = ();
+	fn trace(&self, input: Vec2<T>, cache: &mut Self::Cache) -> T {
+		Segment {
+			a: self.a,
+			b: self.b,
+		}
+		.trace(input, cache)
+			- self.radius
+	}
+}
+
+/// axis aligned, centered on the origin
+#[derive(Debug, Clone, Copy)]
+pub struct Ellipse<T> {
+	pub radii: Vec2<T>,
+}
+impl<T: Real> Trace<Vec2<T>, T> for Ellipse<T> {
+	type Cache = ();
+	fn trace(&self, input: Vec2<T>, _cache: &mut Self::Cache) -> T {
+		let (mut p, mut ab) = (input.map(T::abs), self.radii);
+		if p.x > p.y {
+			p = p.yx();
+			ab = ab.yx();
+		}
+		let l = ab.y * ab.y - ab.x * ab.x;
+		// the closed form divides by `l`
+		if l.abs() <= T::epsilon() * ab.y * ab.y {
+			return p.magnitude() - ab.x;
+		}
+		let (two, three) = (c::<T>(2.0), c::<T>(3.0));
+		let m = ab.x * p.x / l;
+		let m2 = m * m;
+		let n = ab.y * p.y / l;
+		let n2 = n * n;
+		let cc = (m2 + n2 - T::one()) / three;
+		let c3 = cc * cc * cc;
+		let q = c3 + m2 * n2 * two;
+		let d = c3 + m2 * n2;
+		let g = m + m * n2;
+		let co = if d < T::zero() {
+			let h = (q / c3).max(-T::one()).min(T::one()).acos() / three;
+			let (s, t) = (h.cos(), h.sin() * three.sqrt());
+			let rx = (-cc * (s + t + two) + m2).sqrt();
+			let ry = (-cc * (s - t + two) + m2).sqrt();
+			(ry + l.signum() * rx + g.abs() / (rx * ry) - m) / two
+		} else {
+			let h = two * m * n * d.sqrt();
+			let s = (q + h).cbrt();
+			let u = (q - h).cbrt();
+			let rx = -s - u - cc * c(4.0) + two * m2;
+			let ry = (s - u) * three.sqrt();
+			let rm = (rx * rx + ry * ry).sqrt();
+			(ry / (rm - rx).sqrt() + two * g / rm - m) / two
+		};
+		let co = co.max(T::zero()).min(T::one());
+		let r = ab * Vec2::new(co, (T::one() - co * co).sqrt());
+		(r - p).magnitude() * (p.y - r.y).signum()
+	}
+}
+
+/// a circular arc with thickness, symmetric around the positive y axis, `aperture` is the half angle in radians
+#[derive(Debug, Clone, Copy)]
+pub struct Arc<T> {
+	pub radius: T,
+	pub aperture: T,
+	pub thickness: T,
+}
+impl<T: Real> Trace<Vec2<T>, T> for Arc<T> {
+	type Cache = ();
+	fn trace(&self, input: Vec2<T>, _cache: &mut Self::Cache) -> T {
+		let p = Vec2::new(input.x.abs(), input.y);
+		let (sin, cos) = self.aperture.sin_cos();
+		let dist = match cos * p.x > sin * p.y {
+			true => (p - Vec2::new(sin, cos) * self.radius).magnitude(),
+			false => (p.magnitude() - self.radius).abs(),
+		};
+		dist - self.thickness / c(2.0)
+	}
+}
+
+/// a full circle outline
+#[derive(Debug, Clone, Copy)]
+pub struct Ring<T> {
+	pub radius: T,
+	pub thickness: T,
+}
+impl<T: Real> Trace<Vec2<T>, T> for Ring<T> {
+	type Cache = ();
+	fn trace(&self, input: Vec2<T>, _cache: &mut Self::Cache) -> T {
+		(input.magnitude() - self.radius).abs() - self.thickness / c(2.0)
+	}
+}
+
+/// a circular sector, symmetric around the positive y axis, `aperture` is the half angle in radians
+#[derive(Debug, Clone, Copy)]
+pub struct Pie<T> {
+	pub radius: T,
+	pub aperture: T,
+}
+impl<T: Real> Trace<Vec2<T>, T> for Pie<T> {
+	type Cache = ();
+	fn trace(&self, input: Vec2<T>, _cache: &mut Self::Cache) -> T {
+		let p = Vec2::new(input.x.abs(), input.y);
+		let (sin, cos) = self.aperture.sin_cos();
+		let sc = Vec2::new(sin, cos);
+		let l = p.magnitude() - self.radius;
+		let m = (p - sc * p.dot(sc).max(T::zero()).min(self.radius)).magnitude();
+		l.max(m * (cos * p.x - sin * p.y).signum())
+	}
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Triangle<T> {
+	pub a: Vec2<T>,
+	pub b: Vec2<T>,
+	pub c: Vec2<T>,
+}
+impl<T: Real> Trace<Vec2<T>, T> for Triangle<T> {
+	type Cache = ();
+	fn trace(&self, input: Vec2<T>, _cache: &mut Self::Cache) -> T {
+		let cross = |a: Vec2<T>, b: Vec2<T>| a.x * b.y - a.y * b.x;
+		let edges = [
+			(self.a, self.b - self.a),
+			(self.b, self.c - self.b),
+			(self.c, self.a - self.c),
+		];
+		// works for either winding
+		let s = cross(edges[0].1, edges[2].1).signum();
+		let (dist, side) = edges
+			.into_iter()
+			.map(|(start, e)| {
+				let v = input - start;
+				let len = e.dot(e);
+				let h = match len > T::zero() {
+					true => (v.dot(e) / len).max(T::zero()).min(T::one()),
+					false => T::zero(),
+				};
+				let pq = v - e * h;
+				(pq.dot(pq), s * (v.x * e.y - v.y * e.x))
+			})
+			.fold((T::max_value(), T::max_value()), |(d, s), (d2, s2)| {
+				(d.min(d2), s.min(s2))
+			});
+		-dist.sqrt() * side.signum()
+	}
+}
+
+/// `sides` corners on a circle of `radius`, one of them on the positive y axis
+#[derive(Debug, Clone, Copy)]
+pub struct RegularPolygon<T> {
+	pub radius: T,
+	pub sides: u32,
+}
+impl<T: Real> Trace<Vec2<T>, T> for RegularPolygon<T> {
+	type Cache = ();
+	fn trace(&self, input: Vec2<T>, cache: &mut Self::Cache) -> T {
+		// a star with the sharpness of a polygon
+		Star {
+			radius: self.radius,
+			points: self.sides.max(3),
+			sharpness: c(2.0),
+		}
+		.trace(input, cache)
+	}
+}
+
+/// `points` tips on a circle of `radius`, one of them on the positive y axis.
+/// `sharpness` goes from 2 (a regular polygon with `points` corners) to `points` (the edges meet in the center)
+#[derive(Debug, Clone, Copy)]
+pub struct Star<T> {
+	pub radius: T,
+	pub points: u32,
+	pub sharpness: T,
+}
+impl<T: Real> Trace<Vec2<T>, T> for Star<T> {
+	type Cache = ();
+	fn trace(&self, input: Vec2<T>, _cache: &mut Self::Cache) -> T {
+		let pi = c::<T>(std::f64::consts::PI);
+		let an = pi / c(self.points.max(2) as f64);
+		let en = pi / self.sharpness.max(c(2.0)).min(c(self.points.max(2) as f64));
+		let acs = Vec2::new(an.cos(), an.sin());
+		let ecs = Vec2::new(en.cos(), en.sin());
+		// fold into a single half sector
+		let bn = modulo(input.x.atan2(input.y), an * c(2.0)) - an;
+		let p = Vec2::new(bn.cos(), bn.sin().abs()) * input.magnitude() - acs * self.radius;
+		let h = (-p.dot(ecs))
+			.max(T::zero())
+			.min(self.radius * acs.y / ecs.y);
+		let p = p + ecs * h;
+		p.magnitude() * p.x.signum()
+	}
+}
+
+impl_describe! {
+	Rect "Rect" { half_size }
+	RoundedRect "RoundedRect" { half_size radius }
+	Segment "Segment" { a b }
+	Capsule "Capsule" { a b radius }
+	Ellipse "Ellipse" { radii }
+	Arc "Arc" { radius aperture thickness }
+	Ring "Ring" { radius thickness }
+	Pie "Pie" { radius aperture }
+	Triangle "Triangle" { a b c }
+	RegularPolygon "RegularPolygon" { radius sides }
+	Star "Star" { radius points sharpness }
+}
@@ -0,0 +1,50 @@
+use std::io::{self, Write};
+use std::ops::ControlFlow;
+
+use omnitrace::sink::Sink;
+use omnitrace_math::prelude::*;
+
+/// streams rows into a png as they are traced
+pub struct PngSink<W: Write + 'static> {
+	writer: png::StreamWriter<'static, W>,
+	width: usize,
+	error: Option<io::Error>,
+}
+impl<W: Write + 'static> PngSink<W> {
+	pub fn new(w: W, size: Extent2<u32>) -> io::Result<Self> {
+		let mut encoder = png::Encoder::new(w, size.w, size.h);
+		encoder.set_color(png::ColorType::Rgba);
+		encoder.set_depth(png::BitDepth::Eight);
+		encoder.set_source_gamma(png::ScaledFloat::from_scaled(45455));
+		Ok(Self {
+			writer: encoder.write_header()?.into_stream_writer()?,
+			width: size.w as usize,
+			error: None,
+		})
+	}
+}
+impl<W: Write + 'static> Sink<palette::Srgba<u8>> for PngSink<W> {
+	type Output = io::Result<()>;
+	fn chunk_len(&self) -> usize {
+		// whole rows, at least 4096 pixels
+		self.width * (4096 / self.width.max(1)).max(1)
+	}
+	fn accept(&mut self, chunk: Vec<palette::Srgba<u8>>) -> ControlFlow<()> {
+		match self
+			.writer
+			.write_all(palette::cast::into_component_slice(&chunk))
+		{
+			Ok(()) => ControlFlow::Continue(()),
+			Err(e) => {
+				self.error = Some(e);
+				ControlFlow::Break(())
+			}
+		}
+	}
+	fn finish(self) -> Self::Output {
+		match self.error {
+			Some(e) => Err(e),
+			None => Ok(self.writer.finish()?),
+		}
+	}
+}
@@ -0,0 +1,352 @@
+//! outlines with a width, see [`TraceExtStroke::stroke`] and [`Path::stroke`]
+
+use std::fmt;
+
+use omnitrace::describe::Node;
+use omnitrace::prelude::*;
+use omnitrace_math::prelude::*;
+
+use crate::ext::{SdfOnion, TraceExtSdfModifiers, Width};
+use crate::path::{segment_distance_squared, Path, Subpath};
+use crate::shapes::Rect;
+
+/// how two segments of a [`Path`] meet
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Join {
+	/// falls back to [`Join::Bevel`] past the miter limit
+	#[default]
+	Miter,
+	Round,
+	Bevel,
+}
+
+/// how the open ends of a [`Path`] and its dashes look
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Cap {
+	#[default]
+	Butt,
+	Round,
+	/// extends past the end by half the width
+	Square,
+}
+
+/// defaults like svg, miter joins with a limit of 4, butt caps, no dashes
+#[derive(Debug, Clone)]
+pub struct StrokeStyle<V> {
+	width: Width<V>,
+	join: Join,
+	cap: Cap,
+	miter_limit: V,
+	dashes: Vec<V>,
+	dash_offset: V,
+}
+impl<V: Real> StrokeStyle<V> {
+	/// `width` in the units of the shape
+	pub fn new(width: V) -> Self {
+		Self {
+			width: Width::Units(width),
+			join: Join::default(),
+			cap: Cap::default(),
+			miter_limit: V::from(4.0).unwrap(),
+			dashes: Vec::new(),
+			dash_offset: V::zero(),
+		}
+	}
+	/// `width` in output pixels, read from [`crate::SCALE`] while tracing, so it doesn't change with zoom
+	pub fn pixels(width: V) -> Self {
+		Self {
+			width: Width::Pixels(width),
+			..Self::new(width)
+		}
+	}
+	/// one pixel wide
+	pub fn hairline() -> Self {
+		Self::pixels(V::one())
+	}
+	pub fn join(self, join: Join) -> Self {
+		Self { join, ..self }
+	}
+	pub fn cap(self, cap: Cap) -> Self {
+		Self { cap, ..self }
+	}
+	/// the longest miter as a multiple of the width
+	pub fn miter_limit(self, miter_limit: V) -> Self {
+		Self {
+			miter_limit,
+			..self
+		}
+	}
+	/// alternating dash and gap lengths, repeated twice if odd like svg, `offset` shifts the pattern along the path
+	pub fn dash(self, pattern: impl IntoIterator<Item = V>, offset: V) -> Self {
+		let mut dashes = pattern
+			.into_iter()
+			.map(|v| v.max(V::zero()))
+			.collect::<Vec<_>>();
+		if dashes.len() % 2 == 1 {
+			dashes.extend_from_within(..);
+		}
+		Self {
+			dashes,
+			dash_offset: offset,
+			..self
+		}
+	}
+}
+impl<V: fmt::Debug> StrokeStyle<V> {
+	fn describe_into(&self, node: Node) -> Node {
+		let node = node
+			.param("width", self.width.describe())
+			.param("join", format!("{:?}", self.join))
+			.param("cap", format!("{:?}", self.cap));
+		match self.dashes.is_empty() {
+			true => node,
+			false => node.param(
+				"dash",
+				format!("{:?} + {:?}", self.dashes, self.dash_offset),
+			),
+		}
+	}
+}
+
+pub trait TraceExtStroke<V: Real>: Trace<Vec2<V>, V> {
+	/// the outline of any distance field, an [`SdfOnion`] that can be sized in pixels.
+	/// joins, caps and dashes need the outline itself, use [`Path::stroke`] for those
+	fn stroke(self, width: Width<V>) -> SdfOnion<Self, V>
+	where
+		Self: Sized,
+	{
+		self.onion(width)
+	}
+}
+impl<V: Real, T: Trace<Vec2<V>, V>> TraceExtStroke<V> for T {}
+
+/// a [`Path`] with joins, caps and dashes, the dashes are cut when it's built
+#[derive(Debug, Clone)]
+pub struct PathStroke<V> {
+	pieces: Vec<Subpath<V>>,
+	style: StrokeStyle<V>,
+}
+impl<V: Real> Path<V> {
+	/// open subpaths stay open, the fill rule doesn't matter
+	pub fn stroke(self, style: StrokeStyle<V>) -> PathStroke<V> {
+		let mut pieces = Vec::new();
+		for sub in self.subpaths.into_iter().filter(|v| v.drawn) {
+			match style.dashes.iter().any(|&v| v > V::zero()) {
+				true => dash(&sub, &style.dashes, style.dash_offset, &mut pieces),
+				false => pieces.push(sub),
+			}
+		}
+		for piece in &mut pieces {
+			piece.points.dedup();
+			if piece.closed && piece.points.len() > 1 && piece.points.last() == piece.points.first()
+			{
+				piece.points.pop();
+			}
+		}
+		PathStroke { pieces, style }
+	}
+}
+
+/// cuts `sub` into open pieces, restarting the pattern like svg does for every subpath
+fn dash<V: Real>(sub: &Subpath<V>, pattern: &[V], offset: V, pieces: &mut Vec<Subpath<V>>) {
+	let mut points = sub.points.clone();
+	if sub.closed {
+		points.push(points[0]);
+	}
+	let total = pattern.iter().fold(V::zero(), |a, &b| a + b);
+	let mut offset = offset % total;
+	if offset < V::zero() {
+		offset = offset + total;
+	}
+	let mut i = 0;
+	while offset >= pattern[i] {
+		offset = offset - pattern[i];
+		i = (i + 1) % pattern.len();
+	}
+	let mut left = pattern[i] - offset;
+	let starts_on = i % 2 == 0;
+	let first = pieces.len();
+	let mut current = match starts_on {
+		true => vec![points[0]],
+		false => Vec::new(),
+	};
+	let mut cut = false;
+	for w in points.windows(2) {
+		let (a, b) = (w[0], w[1]);
+		let len = (b - a).magnitude();
+		let mut pos = V::zero();
+		while len - pos > left {
+			pos = pos + left;
+			let p = a + (b - a) * (pos / len);
+			match i % 2 == 0 {
+				true => {
+					current.push(p);
+					pieces.push(Subpath {
+						points: std::mem::take(&mut current),
+						closed: false,
+						drawn: true,
+					});
+				}
+				false => current = vec![p],
+			}
+			cut = true;
+			i = (i + 1) % pattern.len();
+			left = pattern[i];
+		}
+		left = left - (len - pos);
+		if i % 2 == 0 {
+			current.push(b);
+		}
+	}
+	let ends_on = i % 2 == 0;
+	if !cut {
+		if ends_on {
+			pieces.push(sub.clone());
+		}
+		return;
+	}
+	if ends_on {
+		// a dash over the start of a closed subpath is one piece
+		if sub.closed && starts_on && pieces.len() > first {
+			let head = pieces.remove(first);
+			current.extend_from_slice(&head.points[1..]);
+		}
+		pieces.push(Subpath {
+			points: current,
+			closed: false,
+			drawn: true,
+		});
+	}
+}
+
+/// the stroke of a single segment, `ext` lengthens it at the start and end
+fn segment_box<V: Real>(p: Vec2<V>, a: Vec2<V>, b: Vec2<V>, h: V, ext: (V, V)) -> V {
+	let two = V::one() + V::one();
+	let len = (b - a).magnitude();
+	let d = (b - a) / len;
+	let (pa, n) = (p - a, Vec2::new(-d.y, d.x));
+	let (u, v) = (pa.dot(d), pa.dot(n));
+	let mid = (len + ext.1 - ext.0) / two;
+	let half_len = (len + ext.0 + ext.1) / two;
+	let q = Vec2::new((u - mid).abs() - half_len, v.abs() - h);
+	q.map(|v| v.max(V::zero())).magnitude() + q.x.max(q.y).min(V::zero())
+}
+
+/// a convex polygon in either winding, a single shape so there's no seam inside of it
+fn convex<V: Real>(p: Vec2<V>, points: &[Vec2<V>]) -> V {
+	let mut dist = V::max_value();
+	let (mut left, mut right) = (false, false);
+	for (i, &a) in points.iter().enumerate() {
+		let b = points[(i + 1) % points.len()];
+		dist = dist.min(segment_distance_squared(p, a, b));
+		let side = (b.x - a.x) * (p.y - a.y) - (b.y - a.y) * (p.x - a.x);
+		left |= side > V::zero();
+		right |= side < V::zero();
+	}
+	match left && right {
+		true => dist.sqrt(),
+		false => -dist.sqrt(),
+	}
+}
+
+impl<V: Real> PathStroke<V> {
+	fn join(&self, p: Vec2<V>, v: Vec2<V>, d1: Vec2<V>, d2: Vec2<V>, h: V) -> V {
+		if self.style.join == Join::Round {
+			return (p - v).magnitude() - h;
+		}
+		// straight on or turning back, there's no wedge to fill
+		let cross = d1.x * d2.y - d1.y * d2.x;
+		if cross.abs() <= V::epsilon() {
+			return V::max_value();
+		}
+		// normals on the outer side of the turn
+		let side = if cross > V::zero() {
+			V::one()
+		} else {
+			-V::one()
+		};
+		let (n1, n2) = (Vec2::new(d1.y, -d1.x) * side, Vec2::new(d2.y, -d2.x) * side);
+		let (a, b) = (v + n1 * h, v + n2 * h);
+		let miter = n1 + n2;
+		let ratio = (V::one() + V::one()) / miter.magnitude();
+		match self.style.join == Join::Miter && ratio <= self.style.miter_limit {
+			true => {
+				let m = v + miter.normalized() * (h * ratio);
+				convex(p, &[v, a, m, b])
+			}
+			false => convex(p, &[v, a, b]),
+		}
+	}
+	fn piece(&self, p: Vec2<V>, piece: &Subpath<V>, h: V) -> V {
+		let points = &piece.points;
+		let n = points.len();
+		// a lone point only shows with a cap
+		if n == 1 {
+			return match self.style.cap {
+				Cap::Round => (p - points[0]).magnitude() - h,
+				Cap::Square => Rect {
+					half_size: Vec2::broadcast(h),
+				}
+				.trace(p - points[0], &mut ()),
+				Cap::Butt => V::max_value(),
+			};
+		}
+		let segments = if piece.closed { n } else { n - 1 };
+		let ext = match self.style.cap {
+			Cap::Square if !piece.closed => h,
+			_ => V::zero(),
+		};
+		let dir = |i: usize| (points[(i + 1) % n] - points[i]).normalized();
+		let mut dist = V::max_value();
+		let mut center = V::max_value();
+		for i in 0..segments {
+			let (a, b) = (points[i], points[(i + 1) % n]);
+			let ext = (
+				if i == 0 { ext } else { V::zero() },
+				if i + 1 == segments { ext } else { V::zero() },
+			);
+			dist = dist.min(segment_box(p, a, b, h, ext));
+			center = center.min(segment_distance_squared(p, a, b));
+			if i > 0 || piece.closed {
+				let prev = (i + segments - 1) % segments;
+				dist = dist.min(self.join(p, a, dir(prev), dir(i), h));
+			}
+		}
+		let center = center.sqrt();
+		if !piece.closed && self.style.cap == Cap::Round {
+			dist = dist.min(center - h);
+		}
+		// zero is also on the seams between two parts, like a butt end and the join after it
+		if dist > V::zero() {
+			return dist;
+		}
+		// every box is only as deep as half its length, so on flattened curves the depth comes
+		// from the distance to the center line and the end faces instead, both lie outside of it
+		let mut depth = h - center;
+		if !piece.closed && self.style.cap != Cap::Round {
+			let (d0, d1) = (dir(0), dir(n - 2));
+			for (end, d) in [(points[0] - d0 * ext, d0), (points[n - 1] + d1 * ext, d1)] {
+				let normal = Vec2::new(-d.y, d.x) * h;
+				depth = depth.min(segment_distance_squared(p, end - normal, end + normal).sqrt());
+			}
+		}
+		dist.min(-depth)
+	}
+}
+impl<V: Real> Trace<Vec2<V>, V> for PathStroke<V> {
+	type Cache = ();
+	fn trace(&self, input: Vec2<V>, _cache: &mut Self::Cache) -> V {
+		let h = self.style.width.half();
+		self.pieces
+			.iter()
+			.map(|piece| self.piece(input, piece, h))
+			.fold(V::max_value(), V::min)
+	}
+}
+impl<V: fmt::Debug> Describe for PathStroke<V> {
+	fn describe(&self) -> Node {
+		self.style
+			.describe_into(Node::new("PathStroke"))
+			.param("pieces", self.pieces.len())
+	}
+}
@@ -0,0 +1,187 @@
+//! sampling images inside a graph
+
+use std::io;
+use std::path::Path;
+
+use omnitrace::describe::Node;
+use omnitrace::prelude::*;
+use omnitrace_math::prelude::*;
+
+use crate::image::{premultiply, resize_premultiplied, unpremultiply};
+use crate::Image;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Filter {
+	Nearest,
+	#[default]
+	Bilinear,
+	/// Catmull-Rom
+	Bicubic,
+}
+
+/// what's outside of the texture
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WrapMode {
+	#[default]
+	Clamp,
+	Repeat,
+	Mirror,
+}
+impl WrapMode {
+	fn apply(self, i: i64, len: u32) -> usize {
+		let len = len as i64;
+		let i = match self {
+			Self::Clamp => i.clamp(0, len - 1),
+			Self::Repeat => i.rem_euclid(len),
+			Self::Mirror => {
+				let i = i.rem_euclid(2 * len);
+				if i < len {
+					i
+				} else {
+					2 * len - 1 - i
+				}
+			}
+		};
+		i as usize
+	}
+}
+
+#[derive(Debug, Clone)]
+struct Level {
+	size: Extent2<u32>,
+	pixels: Vec<Vec4<f32>>,
+}
+impl Level {
+	fn texel(&self, x: i64, y: i64, wrap: WrapMode) -> Vec4<f32> {
+		let x = wrap.apply(x, self.size.w);
+		let y = wrap.apply(y, self.size.h);
+		self.pixels[y * self.size.w as usize + x]
+	}
+	/// `pos` is in texels of the first level
+	fn sample(
+		&self,
+		pos: Vec2<f32>,
+		base: Extent2<u32>,
+		filter: Filter,
+		wrap: WrapMode,
+	) -> Vec4<f32> {
+		let pos = pos * Vec2::<f32>::from(self.size.as_()) / Vec2::<f32>::from(base.as_());
+		match filter {
+			Filter::Nearest => self.texel(pos.x.floor() as i64, pos.y.floor() as i64, wrap),
+			Filter::Bilinear => {
+				// texel centers are at .5
+				let pos = pos - 0.5;
+				let (x, y) = (pos.x.floor() as i64, pos.y.floor() as i64);
+				let f = pos - pos.floor();
+				let top = Lerp::lerp(self.texel(x, y, wrap), self.texel(x + 1, y, wrap), f.x);
+				let bottom = Lerp::lerp(
+					self.texel(x, y + 1, wrap),
+					self.texel(x + 1, y + 1, wrap),
+					f.x,
+				);
+				Lerp::lerp(top, bottom, f.y)
+			}
+			Filter::Bicubic => {
+				let pos = pos - 0.5;
+				let (x, y) = (pos.x.floor() as i64, pos.y.floor() as i64);
+				let f = pos - pos.floor();
+				let (wx, wy) = (catmull_rom(f.x), catmull_rom(f.y));
+				let mut acc = Vec4::<f32>::zero();
+				for (j, wy) in wy.into_iter().enumerate() {
+					for (i, wx) in wx.into_iter().enumerate() {
+						acc += self.texel(x + i as i64 - 1, y + j as i64 - 1, wrap) * (wx * wy);
+					}
+				}
+				// the negative lobes can overshoot
+				acc.map(|v| v.max(0.0))
+			}
+		}
+	}
+}
+
+fn catmull_rom(t: f32) -> [f32; 4] {
+	let (t2, t3) = (t * t, t * t * t);
+	[
+		(-t3 + 2.0 * t2 - t) * 0.5,
+		(3.0 * t3 - 5.0 * t2 + 2.0) * 0.5,
+		(-3.0 * t3 + 4.0 * t2 + t) * 0.5,
+		(t3 - t2) * 0.5,
+	]
+}
+
+/// an image as a trace, coordinates are in texels of the full resolution image, texel `(0, 0)` covers `[0, 1)²`.
+/// a mip level is picked from [`crate::SCALE`], so minified textures don't alias
+#[derive(Debug, Clone)]
+pub struct Texture {
+	levels: Vec<Level>,
+	filter: Filter,
+	wrap: WrapMode,
+}
+impl Texture {
+	/// builds every mip level down to 1x1, an empty image has none and traces as transparent
+	pub fn new(image: &Image) -> Self {
+		let mut levels = vec![Level {
+			size: image.size(),
+			pixels: image.pixels().iter().copied().map(premultiply).collect(),
+		}];
+		// an empty image has no mips to build
+		while let Some(last) = levels.last().filter(|v| v.size.product() > 1) {
+			let size = last.size.map(|v| (v / 2).max(1));
+			let pixels = resize_premultiplied(&last.pixels, last.size, size);
+			levels.push(Level { size, pixels });
+		}
+		Self {
+			levels,
+			filter: Filter::default(),
+			wrap: WrapMode::default(),
+		}
+	}
+	pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+		Ok(Self::new(&Image::load(path)?))
+	}
+	pub fn filter(self, filter: Filter) -> Self {
+		Self { filter, ..self }
+	}
+	pub fn wrap(self, wrap: WrapMode) -> Self {
+		Self { wrap, ..self }
+	}
+	pub fn size(&self) -> Extent2<u32> {
+		self.levels[0].size
+	}
+}
+
+impl<V: Real> Trace<Vec2<V>, Color> for Texture {
+	type Cache = ();
+	fn trace(&self, input: Vec2<V>, _cache: &mut Self::Cache) -> Color {
+		if self.size().product() == 0 {
+			return Color::NONE;
+		}
+		let pos = input.map(|v| v.to_f32().unwrap());
+		// texels covered by a pixel
+		let scale = crate::SCALE.get();
+		let footprint = scale.cols.x.magnitude().max(scale.cols.y.magnitude()) as f32;
+		let lod = footprint.log2().clamp(0.0, (self.levels.len() - 1) as f32);
+		let base = self.size();
+		let sample = |level: usize| self.levels[level].sample(pos, base, self.filter, self.wrap);
+		let color = match self.filter {
+			Filter::Nearest => sample(lod.round() as usize),
+			_ => {
+				let level = lod.floor() as usize;
+				match level + 1 < self.levels.len() {
+					true => Lerp::lerp(sample(level), sample(level + 1), lod.fract()),
+					false => sample(level),
+				}
+			}
+		};
+		unpremultiply(color)
+	}
+}
+impl Describe for Texture {
+	fn describe(&self) -> Node {
+		let size = self.size();
+		Node::new("Texture")
+			.param("size", format!("{}x{}", size.w, size.h))
+			.param("filter", format!("{:?}", self.filter))
+			.param("wrap", format!("{:?}", self.wrap))
+	}
+}
@@ -0,0 +1,77 @@
+//! type-erased traces, for building graphs at runtime
+
+use std::any::Any;
+use std::fmt;
+
+use crate::describe::{Describe, Node};
+use crate::Trace;
+
+macro_rules! impl_boxed {
+	($(
+		$(#[$meta:meta])*
+		$boxed:ident $dyn_trace:ident $cache:ident ($($cache_bound:tt)*) ($($trace_bound:tt)*)
+	)*) => {$(
+		/// object-safe version of [`Trace`], implemented for every trace with a `'static` cache
+		pub trait $dyn_trace<I, O> {
+			fn trace_dyn(&self, input: I, cache: &mut $cache) -> O;
+			fn type_name(&self) -> &'static str;
+		}
+		impl<I, O, T: Trace<I, O>> $dyn_trace<I, O> for T
+		where
+			T::Cache: 'static $($cache_bound)*,
+		{
+			fn trace_dyn(&self, input: I, cache: &mut $cache) -> O {
+				if !cache.0.as_ref().is_some_and(|v| v.is::<T::Cache>()) {
+					cache.0 = Some(Box::<T::Cache>::default());
+				}
+				let cache = cache.0.as_mut().and_then(|v| v.downcast_mut()).unwrap();
+				self.trace(input, cache)
+			}
+			fn type_name(&self) -> &'static str {
+				std::any::type_name::<T>()
+			}
+		}
+
+		/// type-erased cache, created on first use
+		#[derive(Default)]
+		pub struct $cache(Option<Box<dyn Any $($cache_bound)*>>);
+		impl fmt::Debug for $cache {
+			fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+				f.write_str(stringify!($cache))
+			}
+		}
+
+		$(#[$meta])*
+		pub struct $boxed<I, O>(Box<dyn $dyn_trace<I, O> $($trace_bound)*>);
+		impl<I, O> $boxed<I, O> {
+			pub fn new<T: $dyn_trace<I, O> $($trace_bound)* + 'static>(trace: T) -> Self {
+				Self(Box::new(trace))
+			}
+		}
+		impl<I, O> Trace<I, O> for $boxed<I, O> {
+			type Cache = $cache;
+			#[cfg_attr(feature = "inline", inline(always))]
+			fn trace(&self, input: I, cache: &mut Self::Cache) -> O {
+				self.0.trace_dyn(input, cache)
+			}
+		}
+		// the inner trace isn't required to be describable
+		impl<I, O> Describe for $boxed<I, O> {
+			fn describe(&self) -> Node {
+				Node::new(stringify!($boxed)).param("type", self.0.type_name())
+			}
+		}
+		impl<I, O> fmt::Debug for $boxed<I, O> {
+			fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+				f.write_str(stringify!($boxed))
+			}
+		}
+	)*};
+}
+
+impl_boxed! {
+	/// a trace behind a pointer, the cache is type-erased so any trace fits
+	BoxedTrace DynTrace DynCache () ()
+	/// [`BoxedTrace`] that can be used with [`crate::iterate_parallel`]
+	BoxedTraceSync DynTraceSync DynCacheSend (+ Send) (+ Send + Sync)
+}
@@ -0,0 +1,55 @@
+//! stopping long-running iterations from another thread
+
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use crate::describe::{Describe, Node};
+use crate::Trace;
+
+/// shared cancellation flag, clones refer to the same flag
+#[derive(Debug, Clone, Default)]
+pub struct Cancel(Arc<AtomicBool>);
+impl Cancel {
+	pub fn new() -> Self {
+		Self::default()
+	}
+	pub fn cancel(&self) {
+		self.0.store(true, Ordering::Relaxed);
+	}
+	#[cfg_attr(feature = "inline", inline(always))]
+	pub fn is_cancelled(&self) -> bool {
+		self.0.load(Ordering::Relaxed)
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cancelled;
+impl fmt::Display for Cancelled {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.write_str("cancelled")
+	}
+}
+impl std::error::Error for Cancelled {}
+
+/// checks the flag before every input, use with [`crate::iterate_try`] or [`crate::sink::TrySink`]
+#[derive(Debug, Clone)]
+pub struct CancelOn<T> {
+	pub(crate) parent: T,
+	pub(crate) cancel: Cancel,
+}
+impl<I, O, T: Trace<I, O>> Trace<I, Result<O, Cancelled>> for CancelOn<T> {
+	type Cache = T::Cache;
+	fn trace(&self, input: I, cache: &mut Self::Cache) -> Result<O, Cancelled> {
+		if self.cancel.is_cancelled() {
+			Err(Cancelled)
+		} else {
+			Ok(self.parent.trace(input, cache))
+		}
+	}
+}
+impl<T: Describe> Describe for CancelOn<T> {
+	fn describe(&self) -> Node {
+		Node::new("CancelOn").child(self.parent.describe())
+	}
+}
@@ -0,0 +1,89 @@
+//! introspection of trace graphs, for debugging & documenting scenes
+
+use std::borrow::Cow;
+use std::fmt;
+
+/// a trace that can describe itself and its children
+pub trait Describe {
+	fn describe(&self) -> Node;
+}
+
+impl<T: Describe> Describe for &T {
+	fn describe(&self) -> Node {
+		(*self).describe()
+	}
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Node {
+	pub label: Cow<'static, str>,
+	pub params: Vec<(Cow<'static, str>, String)>,
+	pub children: Vec<Node>,
+}
+impl Node {
+	pub fn new(label: impl Into<Cow<'static, str>>) -> Self {
+		Self {
+			label: label.into(),
+			params: Vec::new(),
+			children: Vec::new(),
+		}
+	}
+	pub fn param(mut self, name: impl Into<Cow<'static, str>>, value: impl ToString) -> Self {
+		self.params.push((name.into(), value.to_string()));
+		self
+	}
+	pub fn child(mut self, child: Node) -> Self {
+		self.children.push(child);
+		self
+	}
+	/// indented tree, same as `Display`
+	pub fn to_text(&self) -> String {
+		self.to_string()
+	}
+	/// Graphviz source, one graph node per tree node
+	pub fn to_dot(&self) -> String {
+		fn escape(s: &str) -> String {
+			s.replace('\\', "\\\\")
+				.replace('"', "\\\"")
+				.replace('\n', "\\n")
+		}
+		fn write_node(out: &mut String, node: &Node, next_id: &mut usize) -> usize {
+			let id = *next_id;
+			*next_id += 1;
+			let mut label = escape(&node.label);
+			for (name, value) in &node.params {
+				label += &format!("\\n{} = {}", escape(name), escape(value));
+			}
+			*out += &format!("\tn{id} [label=\"{label}\"];\n");
+			for child in &node.children {
+				let child_id = write_node(out, child, next_id);
+				*out += &format!("\tn{id} -> n{child_id};\n");
+			}
+			id
+		}
+		let mut out = String::from("digraph {\n\tnode [shape=box];\n");
+		write_node(&mut out, self, &mut 0);
+		out.push_str("}\n");
+		out
+	}
+	fn fmt_indent(&self, f: &mut fmt::Formatter, depth: usize) -> fmt::Result {
+		write!(f, "{:indent$}{}", "", self.label, indent = 2 * depth)?;
+		for (i, (name, value)) in self.params.iter().enumerate() {
+			let sep = if i == 0 { " (" } else { ", " };
+			write!(f, "{sep}{name} = {value}")?;
+		}
+		if !self.params.is_empty() {
+			f.write_str(")")?;
+		}
+		writeln!(f)?;
+		for child in &self.children {
+			child.fmt_indent(f, depth + 1)?;
+		}
+		Ok(())
+	}
+}
+impl fmt::Display for Node {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		self.fmt_indent(f, 0)
+	}
+}
@@ -1,4 +1,14 @@
-use crate::{PhantomNothing, Trace};
+use std::any::{type_name, Any};
+use std::cell::RefCell;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use fxhash::FxHashMap;
+
+use crate::boxed::{BoxedTrace, BoxedTraceSync};
+use crate::cancel::{Cancel, CancelOn};
+use crate::describe::{Describe, Node};
+use crate::profile::Profile;
+use crate::{PhantomNothing, Trace, TryTrace};
 
 #[derive(Debug, Clone, Copy)]
 pub struct WrapLazy<T, F> {
@@ -14,6 +24,13 @@ impl<I, O, R: Trace<I, O>, F: Fn(T) -> R, T: Clone> Trace<I, O> for WrapLazy<T,
 			.trace(input, &mut cache.1)
 	}
 }
+impl<T: Describe, F> Describe for WrapLazy<T, F> {
+	fn describe(&self) -> Node {
+		Node::new("WrapLazy")
+			.param("fn", type_name::<F>())
+			.child(self.parent.describe())
+	}
+}
 
 #[derive(Debug, Clone, Copy)]
 #[repr(transparent)]
@@ -27,6 +44,11 @@ impl<I, O, T: Trace<I, O>> Trace<I, O> for Cast<T, I, O> {
 		self.parent.trace(input, cache)
 	}
 }
+impl<T: Describe, I, O> Describe for Cast<T, I, O> {
+	fn describe(&self) -> Node {
+		Node::new("Cast").child(self.parent.describe())
+	}
+}
 
 #[derive(Debug, Clone, Copy)]
 pub struct MapIn<T, F, P> {
@@ -40,6 +62,13 @@ impl<I, O, I2, F: Fn(I2) -> I, T: Trace<I, O>> Trace<I2, O> for MapIn<T, F, I> {
 		self.parent.trace((self.func)(input), cache)
 	}
 }
+impl<T: Describe, F, P> Describe for MapIn<T, F, P> {
+	fn describe(&self) -> Node {
+		Node::new("MapIn")
+			.param("fn", type_name::<F>())
+			.child(self.parent.describe())
+	}
+}
 
 #[derive(Debug, Clone, Copy)]
 pub struct MapOut<T, F, P> {
@@ -53,6 +82,13 @@ impl<I, O, O2, F: Fn(O) -> O2, T: Trace<I, O>> Trace<I, O2> for MapOut<T, F, O>
 		(self.func)(self.parent.trace(input, cache))
 	}
 }
+impl<T: Describe, F, P> Describe for MapOut<T, F, P> {
+	fn describe(&self) -> Node {
+		Node::new("MapOut")
+			.param("fn", type_name::<F>())
+			.child(self.parent.describe())
+	}
+}
 
 #[derive(Debug, Clone, Copy)]
 pub struct Out2In<T, U, P> {
@@ -67,6 +103,159 @@ impl<I, O, O2, U: Trace<O, O2>, T: Trace<I, O>> Trace<I, O2> for Out2In<T, U, O>
 			.trace(self.parent.trace(input, &mut cache.0), &mut cache.1)
 	}
 }
+impl<T: Describe, U: Describe, P> Describe for Out2In<T, U, P> {
+	fn describe(&self) -> Node {
+		Node::new("Out2In")
+			.child(self.parent.describe())
+			.child(self.then.describe())
+	}
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Fanout<T, U> {
+	parent: T,
+	other: U,
+}
+impl<I: Clone, O, O2, T: Trace<I, O>, U: Trace<I, O2>> Trace<I, (O, O2)> for Fanout<T, U> {
+	type Cache = (T::Cache, U::Cache);
+	fn trace(&self, input: I, cache: &mut Self::Cache) -> (O, O2) {
+		(
+			self.parent.trace(input.clone(), &mut cache.0),
+			self.other.trace(input, &mut cache.1),
+		)
+	}
+}
+impl<T: Describe, U: Describe> Describe for Fanout<T, U> {
+	fn describe(&self) -> Node {
+		Node::new("Fanout")
+			.child(self.parent.describe())
+			.child(self.other.describe())
+	}
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Select<P, T, U> {
+	pred: P,
+	then: T,
+	otherwise: U,
+}
+impl<I: Clone, O, P: Trace<I, bool>, T: Trace<I, O>, U: Trace<I, O>> Trace<I, O>
+	for Select<P, T, U>
+{
+	type Cache = (P::Cache, T::Cache, U::Cache);
+	fn trace(&self, input: I, cache: &mut Self::Cache) -> O {
+		if self.pred.trace(input.clone(), &mut cache.0) {
+			self.then.trace(input, &mut cache.1)
+		} else {
+			self.otherwise.trace(input, &mut cache.2)
+		}
+	}
+}
+impl<P: Describe, T: Describe, U: Describe> Describe for Select<P, T, U> {
+	fn describe(&self) -> Node {
+		Node::new("Select")
+			.child(self.pred.describe())
+			.child(self.then.describe())
+			.child(self.otherwise.describe())
+	}
+}
+
+#[derive(Debug, Clone)]
+pub struct Switch<K, T, F, P> {
+	key: K,
+	branches: Box<[T]>,
+	select: F,
+	_phantom: PhantomNothing<P>,
+}
+impl<I: Clone, O, P, F: Fn(&P) -> usize, K: Trace<I, P>, T: Trace<I, O>> Trace<I, O>
+	for Switch<K, T, F, P>
+{
+	type Cache = (K::Cache, Vec<T::Cache>);
+	fn trace(&self, input: I, cache: &mut Self::Cache) -> O {
+		let index = (self.select)(&self.key.trace(input.clone(), &mut cache.0));
+		if cache.1.len() < self.branches.len() {
+			cache.1.resize_with(self.branches.len(), Default::default);
+		}
+		self.branches[index].trace(input, &mut cache.1[index])
+	}
+}
+impl<K: Describe, T: Describe, F, P> Describe for Switch<K, T, F, P> {
+	fn describe(&self) -> Node {
+		self.branches.iter().fold(
+			Node::new("Switch")
+				.param("select", type_name::<F>())
+				.child(self.key.describe()),
+			|node, branch| node.child(branch.describe()),
+		)
+	}
+}
+
+thread_local! {
+	/// last input & output of every [`Shared`] node traced on this thread
+	static SHARED_MEMO: RefCell<FxHashMap<u64, Box<dyn Any>>> = RefCell::new(FxHashMap::default());
+}
+
+/// clones share one memo, so a node used in several branches (a diamond) gets traced once per input
+///
+/// the memo only lives as long as the caches of one `iterate` call, parameters aren't part of it,
+/// don't share nodes that are traced with the same input under different parameter values
+#[derive(Debug, Clone, Copy)]
+pub struct Shared<T> {
+	parent: T,
+	id: u64,
+}
+impl<I, O, T> Trace<I, O> for Shared<T>
+where
+	I: Clone + PartialEq + 'static,
+	O: Clone + 'static,
+	T: Trace<I, O>,
+{
+	type Cache = SharedCache<T::Cache>;
+	fn trace(&self, input: I, cache: &mut Self::Cache) -> O {
+		cache.id = Some(self.id);
+		let hit = SHARED_MEMO.with(|memo| {
+			memo.borrow()
+				.get(&self.id)
+				.and_then(|v| v.downcast_ref::<(I, O)>())
+				.filter(|v| v.0 == input)
+				.map(|v| v.1.clone())
+		});
+		if let Some(output) = hit {
+			return output;
+		}
+		let output = self.parent.trace(input.clone(), &mut cache.inner);
+		SHARED_MEMO.with(|memo| {
+			let mut memo = memo.borrow_mut();
+			match memo.get_mut(&self.id).and_then(|v| v.downcast_mut()) {
+				Some(v) => *v = (input, output.clone()),
+				None => drop(memo.insert(self.id, Box::new((input, output.clone())))),
+			}
+		});
+		output
+	}
+}
+
+/// forgets the memo when dropped, so it doesn't outlive the `iterate` call that filled it
+#[derive(Debug, Default)]
+pub struct SharedCache<C> {
+	inner: C,
+	id: Option<u64>,
+}
+impl<C> Drop for SharedCache<C> {
+	fn drop(&mut self) {
+		if let Some(id) = self.id {
+			let old = SHARED_MEMO.try_with(|memo| memo.borrow_mut().remove(&id));
+			drop(old);
+		}
+	}
+}
+impl<T: Describe> Describe for Shared<T> {
+	fn describe(&self) -> Node {
+		Node::new("Shared")
+			.param("id", self.id)
+			.child(self.parent.describe())
+	}
+}
 
 pub trait TraceExtCore<I, O>: Trace<I, O> {
 	#[cfg_attr(feature = "inline", inline(always))]
@@ -137,5 +326,199 @@ pub trait TraceExtCore<I, O>: Trace<I, O> {
 			_phantom: PhantomNothing::new(),
 		}
 	}
+	/// trace both with the same input
+	#[cfg_attr(feature = "inline", inline(always))]
+	fn fanout<O2, T: Trace<I, O2>>(self, other: T) -> Fanout<Self, T>
+	where
+		Self: Sized,
+	{
+		Fanout {
+			parent: self,
+			other,
+		}
+	}
+	/// only the chosen branch gets traced
+	#[cfg_attr(feature = "inline", inline(always))]
+	fn select<O2, T: Trace<I, O2>, U: Trace<I, O2>>(
+		self,
+		then: T,
+		otherwise: U,
+	) -> Select<Self, T, U>
+	where
+		Self: Sized + Trace<I, bool>,
+	{
+		Select {
+			pred: self,
+			then,
+			otherwise,
+		}
+	}
+	/// n-way [`select`](TraceExtCore::select), `select` turns the key into the index of the branch,
+	/// panics if that's out of range
+	#[cfg_attr(feature = "inline", inline(always))]
+	fn switch<O2, T: Trace<I, O2>, F: Fn(&O) -> usize>(
+		self,
+		branches: impl IntoIterator<Item = T>,
+		select: F,
+	) -> Switch<Self, T, F, O>
+	where
+		Self: Sized,
+	{
+		Switch {
+			key: self,
+			branches: branches.into_iter().collect(),
+			select,
+			_phantom: PhantomNothing::new(),
+		}
+	}
+	#[cfg_attr(feature = "inline", inline(always))]
+	fn share(self) -> Shared<Self>
+	where
+		Self: Sized,
+	{
+		static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+		Shared {
+			parent: self,
+			id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+		}
+	}
+	/// stop at the next input once `cancel` is triggered
+	#[cfg_attr(feature = "inline", inline(always))]
+	fn cancel_on(self, cancel: &Cancel) -> CancelOn<Self>
+	where
+		Self: Sized,
+	{
+		CancelOn {
+			parent: self,
+			cancel: cancel.clone(),
+		}
+	}
+	/// count calls and time spent, see [`crate::profile::report`]
+	#[cfg_attr(feature = "inline", inline(always))]
+	fn profile(self, name: &'static str) -> Profile<Self>
+	where
+		Self: Sized,
+	{
+		Profile { parent: self, name }
+	}
+	#[cfg_attr(feature = "inline", inline(always))]
+	fn boxed(self) -> BoxedTrace<I, O>
+	where
+		Self: Sized + 'static,
+		Self::Cache: 'static,
+	{
+		BoxedTrace::new(self)
+	}
+	#[cfg_attr(feature = "inline", inline(always))]
+	fn boxed_sync(self) -> BoxedTraceSync<I, O>
+	where
+		Self: Sized + Send + Sync + 'static,
+		Self::Cache: Send + 'static,
+	{
+		BoxedTraceSync::new(self)
+	}
 }
 impl<I, O, T: Trace<I, O>> TraceExtCore<I, O> for T {}
+
+#[derive(Debug, Clone, Copy)]
+pub struct AndThen<T, U, P> {
+	parent: T,
+	then: U,
+	_phantom: PhantomNothing<P>,
+}
+impl<I, O, O2, E, U: TryTrace<O, O2, E>, T: TryTrace<I, O, E>> Trace<I, Result<O2, E>>
+	for AndThen<T, U, O>
+{
+	type Cache = (T::Cache, U::Cache);
+	fn trace(&self, input: I, cache: &mut Self::Cache) -> Result<O2, E> {
+		self.then
+			.trace(self.parent.trace(input, &mut cache.0)?, &mut cache.1)
+	}
+}
+impl<T: Describe, U: Describe, P> Describe for AndThen<T, U, P> {
+	fn describe(&self) -> Node {
+		Node::new("AndThen")
+			.child(self.parent.describe())
+			.child(self.then.describe())
+	}
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct MapOk<T, F, P> {
+	parent: T,
+	func: F,
+	_phantom: PhantomNothing<P>,
+}
+impl<I, O, O2, E, F: Fn(O) -> O2, T: TryTrace<I, O, E>> Trace<I, Result<O2, E>> for MapOk<T, F, O> {
+	type Cache = T::Cache;
+	fn trace(&self, input: I, cache: &mut Self::Cache) -> Result<O2, E> {
+		self.parent.trace(input, cache).map(&self.func)
+	}
+}
+impl<T: Describe, F, P> Describe for MapOk<T, F, P> {
+	fn describe(&self) -> Node {
+		Node::new("MapOk")
+			.param("fn", type_name::<F>())
+			.child(self.parent.describe())
+	}
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct MapErr<T, F, P> {
+	parent: T,
+	func: F,
+	_phantom: PhantomNothing<P>,
+}
+impl<I, O, E, E2, F: Fn(E) -> E2, T: TryTrace<I, O, E>> Trace<I, Result<O, E2>>
+	for MapErr<T, F, E>
+{
+	type Cache = T::Cache;
+	fn trace(&self, input: I, cache: &mut Self::Cache) -> Result<O, E2> {
+		self.parent.trace(input, cache).map_err(&self.func)
+	}
+}
+impl<T: Describe, F, P> Describe for MapErr<T, F, P> {
+	fn describe(&self) -> Node {
+		Node::new("MapErr")
+			.param("fn", type_name::<F>())
+			.child(self.parent.describe())
+	}
+}
+
+pub trait TraceExtTry<I, O, E>: TryTrace<I, O, E> {
+	/// like [`out2in`](TraceExtCore::out2in) but skipped on error
+	#[cfg_attr(feature = "inline", inline(always))]
+	fn and_then<O2, T: TryTrace<O, O2, E>>(self, s: T) -> AndThen<Self, T, O>
+	where
+		Self: Sized,
+	{
+		AndThen {
+			parent: self,
+			then: s,
+			_phantom: PhantomNothing::new(),
+		}
+	}
+	#[cfg_attr(feature = "inline", inline(always))]
+	fn map_ok<O2, F: Fn(O) -> O2>(self, f: F) -> MapOk<Self, F, O>
+	where
+		Self: Sized,
+	{
+		MapOk {
+			parent: self,
+			func: f,
+			_phantom: PhantomNothing::new(),
+		}
+	}
+	#[cfg_attr(feature = "inline", inline(always))]
+	fn map_err<E2, F: Fn(E) -> E2>(self, f: F) -> MapErr<Self, F, E>
+	where
+		Self: Sized,
+	{
+		MapErr {
+			parent: self,
+			func: f,
+			_phantom: PhantomNothing::new(),
+		}
+	}
+}
+impl<I, O, E, T: TryTrace<I, O, E>> TraceExtTry<I, O, E> for T {}
@@ -1,16 +1,33 @@
 use std::fmt;
 use std::marker::PhantomData;
 
+use describe::{Describe, Node};
+#[cfg(feature = "parallel")]
+use parameter_const::snapshot::{Snapshot, SnapshotGuard};
 use pretty::bar::Bar;
+use sink::Sink;
 
+pub mod boxed;
+pub mod cancel;
+pub mod describe;
 pub mod ext;
+pub mod param;
+pub mod profile;
+pub mod sink;
+#[cfg(feature = "parallel")]
+pub use rayon;
 
 pub mod prelude {
-	pub use crate::ext::TraceExtCore;
-	pub use crate::{Func, In2Out, Trace};
+	pub use crate::boxed::{BoxedTrace, BoxedTraceSync};
+	pub use crate::describe::Describe;
+	pub use crate::ext::{TraceExtCore, TraceExtTry};
+	pub use crate::param::{read_param, with_param};
+	pub use crate::{Func, In2Out, Trace, TryTrace};
 }
 
-pub struct PhantomNothing<T>(PhantomData<*const T>);
+/// names a type without holding one, like `PhantomData<fn() -> T>` it's covariant and always
+/// `Send + Sync`, so combinators that only remember their input or output type stay usable in parallel
+pub struct PhantomNothing<T>(PhantomData<fn() -> T>);
 impl<T> PhantomNothing<T> {
 	#[cfg_attr(feature = "inline", inline(always))]
 	const fn new() -> Self {
@@ -34,6 +51,10 @@ pub trait Trace<I, O> {
 	fn trace(&self, input: I, cache: &mut Self::Cache) -> O;
 }
 
+/// a trace that can fail, see [`ext::TraceExtTry`] and [`iterate_try`]
+pub trait TryTrace<I, O, E>: Trace<I, Result<O, E>> {}
+impl<I, O, E, T: Trace<I, Result<O, E>>> TryTrace<I, O, E> for T {}
+
 impl<I, O, T: Trace<I, O>> Trace<I, O> for &T {
 	type Cache = T::Cache;
 	fn trace(&self, input: I, cache: &mut Self::Cache) -> O {
@@ -50,6 +71,11 @@ macro_rules! impl_tuple_trace {
 					($(self.$n.trace(input.$n, &mut cache.$n),)*)
 				}
 			}
+			impl<$([<T $n>]: Describe),*> Describe for ($([<T $n>],)*) {
+				fn describe(&self) -> Node {
+					Node::new("Tuple")$(.child(self.$n.describe()))*
+				}
+			}
 		}
 	};
 	($($t:tt)*) => { $(impl_tuple_trace!($t);)* };
@@ -79,8 +105,19 @@ impl<T> Trace<T, T> for In2Out {
 	}
 }
 
+impl Describe for In2Out {
+	fn describe(&self) -> Node {
+		Node::new("In2Out")
+	}
+}
+
 #[derive(Debug, Clone, Copy)]
 pub struct Func<F>(pub F);
+impl<F> Describe for Func<F> {
+	fn describe(&self) -> Node {
+		Node::new("Func").param("fn", std::any::type_name::<F>())
+	}
+}
 
 impl<I, O, F: Fn(I) -> O> Trace<I, O> for Func<F> {
 	type Cache = ();
@@ -104,6 +141,8 @@ where
 	.collect()
 }
 
+/// collects the inputs first so the outputs come back in input order,
+/// inputs that already are an `IndexedParallelIterator`, like ranges, go through [`iterate_parallel_indexed`] without the copy
 #[cfg(feature = "parallel")]
 pub fn iterate_parallel<I, O, V, T>(name: impl Into<String>, i: V, s: T) -> Vec<O>
 where
@@ -113,36 +152,68 @@ where
 	T: Trace<I, O> + Sync,
 	T::Cache: Send,
 {
-	use rayon::iter::{ParallelBridge, ParallelIterator};
-	let bar = Bar::new(name, i.size_hint().1.and_then(|v| u64::try_from(v).ok()));
-	i.par_bridge()
-		.map_init(T::Cache::default, |cache, input| {
-			let output = s.trace(input, cache);
-			bar.increment(1);
-			output
-		})
-		.collect()
+	use rayon::iter::IntoParallelIterator;
+	iterate_parallel_indexed(name, i.collect::<Vec<_>>().into_par_iter(), s)
 }
 
+/// `len` isn't needed anymore, the outputs now come back in input order like [`iterate_parallel`]
+#[deprecated(
+	note = "the outputs weren't in input order, use `iterate_parallel` or `iterate_parallel_indexed`"
+)]
 #[cfg(feature = "parallel")]
-pub fn iterate_parallel_fast<I, O, V, T>(name: impl Into<String>, len: u64, i: V, s: T) -> Vec<O>
+pub fn iterate_parallel_fast<I, O, V, T>(name: impl Into<String>, _len: u64, i: V, s: T) -> Vec<O>
 where
 	I: Send,
 	O: Send,
 	V: rayon::iter::ParallelIterator<Item = I> + Send,
 	T: Trace<I, O> + Sync,
 	T::Cache: Send,
+{
+	use rayon::iter::IntoParallelIterator;
+	iterate_parallel_indexed(name, i.collect::<Vec<_>>().into_par_iter(), s)
+}
+
+/// outputs are always in the same order as the inputs
+#[cfg(feature = "parallel")]
+pub fn iterate_parallel_indexed<I, O, V, T>(name: impl Into<String>, i: V, s: T) -> Vec<O>
+where
+	I: Send,
+	O: Send,
+	V: rayon::iter::IndexedParallelIterator<Item = I>,
+	T: Trace<I, O> + Sync,
+	T::Cache: Send,
+{
+	let bar = Bar::new(name, u64::try_from(i.len()).ok());
+	trace_parallel(&bar, i, &s, &Snapshot::capture())
+}
+
+#[cfg(feature = "parallel")]
+fn trace_parallel<I, O, V, T>(bar: &Bar, i: V, s: &T, snapshot: &Snapshot) -> Vec<O>
+where
+	I: Send,
+	O: Send,
+	V: rayon::iter::IndexedParallelIterator<Item = I>,
+	T: Trace<I, O> + Sync,
+	T::Cache: Send,
 {
 	use rayon::iter::ParallelIterator;
-	let bar = Bar::new(name, len);
-	i.map_init(T::Cache::default, |cache, input| {
-		let output = s.trace(input, cache);
-		bar.increment(1);
-		output
-	})
+	i.map_init(
+		|| worker_init::<I, O, T>(snapshot),
+		|(_, cache), input| {
+			let output = s.trace(input, cache);
+			bar.increment(1);
+			output
+		},
+	)
 	.collect()
 }
 
+/// every worker starts with the parameters set on the calling thread
+#[cfg(feature = "parallel")]
+fn worker_init<I, O, T: Trace<I, O>>(snapshot: &Snapshot) -> (SnapshotGuard, T::Cache) {
+	(snapshot.install(), T::Cache::default())
+}
+
 #[cfg_attr(feature = "inline", inline(always))]
 pub fn iterate<I, O, V, T>(name: impl Into<String>, i: V, s: T) -> Vec<O>
 where
@@ -157,3 +228,657 @@ where
 	#[cfg(not(feature = "parallel"))]
 	return iterate_linear(name, i, s);
 }
+
+pub fn iterate_into_linear<I, O, V, T, S>(
+	name: impl Into<String>,
+	mut i: V,
+	s: T,
+	mut sink: S,
+) -> S::Output
+where
+	V: Iterator<Item = I>,
+	T: Trace<I, O>,
+	S: Sink<O>,
+{
+	let mut cache = T::Cache::default();
+	let bar = Bar::new(name, i.size_hint().1.and_then(|v| u64::try_from(v).ok()));
+	loop {
+		let chunk = i
+			.by_ref()
+			.take(sink.chunk_len().max(1))
+			.map(|input| {
+				let output = s.trace(input, &mut cache);
+				bar.increment(1);
+				output
+			})
+			.collect::<Vec<_>>();
+		if chunk.is_empty() {
+			break;
+		}
+		if sink.accept(chunk).is_break() {
+			drop(bar.finish_on_drop(false));
+			break;
+		}
+	}
+	sink.finish()
+}
+
+/// each chunk is traced in parallel, chunks are handed to the sink in order
+/// while the next one is being traced. the parameters are captured once, when it's called
+#[cfg(feature = "parallel")]
+pub fn iterate_into_parallel<I, O, V, T, S>(
+	name: impl Into<String>,
+	mut i: V,
+	s: T,
+	mut sink: S,
+) -> S::Output
+where
+	I: Send,
+	O: Send,
+	V: Iterator<Item = I>,
+	T: Trace<I, O> + Sync,
+	T::Cache: Send,
+	S: Sink<O>,
+{
+	use rayon::iter::IntoParallelIterator;
+	let bar = Bar::new(name, i.size_hint().1.and_then(|v| u64::try_from(v).ok()));
+	let snapshot = Snapshot::capture();
+	let trace = |inputs: Vec<I>| trace_parallel(&bar, inputs.into_par_iter(), &s, &snapshot);
+	let mut traced = trace(i.by_ref().take(sink.chunk_len().max(1)).collect());
+	while !traced.is_empty() {
+		let inputs = i.by_ref().take(sink.chunk_len().max(1)).collect::<Vec<_>>();
+		let mut next = Vec::new();
+		// the sink runs on this thread, it doesn't have to be `Send`
+		let flow = rayon::in_place_scope(|scope| {
+			if !inputs.is_empty() {
+				scope.spawn(|_| next = trace(inputs));
+			}
+			sink.accept(std::mem::take(&mut traced))
+		});
+		if flow.is_break() {
+			drop(bar.finish_on_drop(false));
+			break;
+		}
+		traced = next;
+	}
+	sink.finish()
+}
+
+#[cfg_attr(feature = "inline", inline(always))]
+pub fn iterate_into<I, O, V, T, S>(name: impl Into<String>, i: V, s: T, sink: S) -> S::Output
+where
+	I: Send,
+	O: Send,
+	V: Iterator<Item = I> + Send,
+	T: Trace<I, O> + Sync,
+	T::Cache: Send,
+	S: Sink<O>,
+{
+	#[cfg(feature = "parallel")]
+	return iterate_into_parallel(name, i, s, sink);
+	#[cfg(not(feature = "parallel"))]
+	return iterate_into_linear(name, i, s, sink);
+}
+
+pub fn iterate_fold_linear<I, O, A, V, T, ID, F>(
+	name: impl Into<String>,
+	i: V,
+	s: T,
+	identity: ID,
+	fold: F,
+) -> A
+where
+	V: Iterator<Item = I>,
+	T: Trace<I, O>,
+	ID: FnOnce() -> A,
+	F: FnMut(A, O) -> A,
+{
+	let mut cache = T::Cache::default();
+	let mut fold = fold;
+	let bar = Bar::new(name, i.size_hint().1.and_then(|v| u64::try_from(v).ok()));
+	i.fold(identity(), |acc, input| {
+		let output = s.trace(input, &mut cache);
+		bar.increment(1);
+		fold(acc, output)
+	})
+}
+
+/// every worker folds its own share starting from `identity`, the results are combined with `merge`
+#[cfg(feature = "parallel")]
+pub fn iterate_fold_parallel<I, O, A, V, T, ID, F, M>(
+	name: impl Into<String>,
+	i: V,
+	s: T,
+	identity: ID,
+	fold: F,
+	merge: M,
+) -> A
+where
+	I: Send,
+	A: Send,
+	V: Iterator<Item = I> + Send,
+	T: Trace<I, O> + Sync,
+	T::Cache: Send,
+	ID: Fn() -> A + Sync + Send,
+	F: Fn(A, O) -> A + Sync + Send,
+	M: Fn(A, A) -> A + Sync + Send,
+{
+	use rayon::iter::{IndexedParallelIterator, IntoParallelIterator, ParallelIterator};
+	let i = i.collect::<Vec<_>>().into_par_iter();
+	let bar = Bar::new(name, u64::try_from(i.len()).ok());
+	let snapshot = Snapshot::capture();
+	// outputs are folded in chunks on the worker that traced them, so they never cross threads
+	let chunk_len = i.len().div_ceil(rayon::current_num_threads() * 16).max(1);
+	i.chunks(chunk_len)
+		.map_init(
+			|| worker_init::<I, O, T>(&snapshot),
+			|(_, cache), chunk| {
+				chunk.into_iter().fold(identity(), |acc, input| {
+					let output = s.trace(input, cache);
+					bar.increment(1);
+					fold(acc, output)
+				})
+			},
+		)
+		.reduce(&identity, &merge)
+}
+
+/// `merge` is only used with the `parallel` feature
+#[cfg_attr(feature = "inline", inline(always))]
+pub fn iterate_fold<I, O, A, V, T, ID, F, M>(
+	name: impl Into<String>,
+	i: V,
+	s: T,
+	identity: ID,
+	fold: F,
+	merge: M,
+) -> A
+where
+	I: Send,
+	A: Send,
+	V: Iterator<Item = I> + Send,
+	T: Trace<I, O> + Sync,
+	T::Cache: Send,
+	ID: Fn() -> A + Sync + Send,
+	F: Fn(A, O) -> A + Sync + Send,
+	M: Fn(A, A) -> A + Sync + Send,
+{
+	#[cfg(feature = "parallel")]
+	return iterate_fold_parallel(name, i, s, identity, fold, merge);
+	#[cfg(not(feature = "parallel"))]
+	{
+		drop(merge);
+		iterate_fold_linear(name, i, s, identity, fold)
+	}
+}
+
+#[cfg_attr(feature = "inline", inline(always))]
+fn reduce_some<O>(reduce: impl Fn(O, O) -> O) -> impl Fn(Option<O>, Option<O>) -> Option<O> {
+	move |a, b| match (a, b) {
+		(Some(a), Some(b)) => Some(reduce(a, b)),
+		(a, b) => a.or(b),
+	}
+}
+
+/// [`None`] if there were no inputs
+pub fn iterate_reduce_linear<I, O, V, T, R>(
+	name: impl Into<String>,
+	i: V,
+	s: T,
+	reduce: R,
+) -> Option<O>
+where
+	V: Iterator<Item = I>,
+	T: Trace<I, O>,
+	R: Fn(O, O) -> O,
+{
+	let reduce = reduce_some(reduce);
+	iterate_fold_linear(name, i, s, || None, |a, b| reduce(a, Some(b)))
+}
+
+/// [`None`] if there were no inputs
+#[cfg(feature = "parallel")]
+pub fn iterate_reduce_parallel<I, O, V, T, R>(
+	name: impl Into<String>,
+	i: V,
+	s: T,
+	reduce: R,
+) -> Option<O>
+where
+	I: Send,
+	O: Send,
+	V: Iterator<Item = I> + Send,
+	T: Trace<I, O> + Sync,
+	T::Cache: Send,
+	R: Fn(O, O) -> O + Sync + Send,
+{
+	let reduce = reduce_some(reduce);
+	iterate_fold_parallel(name, i, s, || None, |a, b| reduce(a, Some(b)), &reduce)
+}
+
+/// [`None`] if there were no inputs
+#[cfg_attr(feature = "inline", inline(always))]
+pub fn iterate_reduce<I, O, V, T, R>(name: impl Into<String>, i: V, s: T, reduce: R) -> Option<O>
+where
+	I: Send,
+	O: Send,
+	V: Iterator<Item = I> + Send,
+	T: Trace<I, O> + Sync,
+	T::Cache: Send,
+	R: Fn(O, O) -> O + Sync + Send,
+{
+	#[cfg(feature = "parallel")]
+	return iterate_reduce_parallel(name, i, s, reduce);
+	#[cfg(not(feature = "parallel"))]
+	return iterate_reduce_linear(name, i, s, reduce);
+}
+
+/// the error that stopped [`iterate_try`] and the index of its input
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IterateError<E> {
+	pub index: usize,
+	pub error: E,
+}
+impl<E: fmt::Display> fmt::Display for IterateError<E> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "input {}: {}", self.index, self.error)
+	}
+}
+impl<E: std::error::Error + 'static> std::error::Error for IterateError<E> {
+	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+		Some(&self.error)
+	}
+}
+
+pub fn iterate_try_linear<I, O, E, V, T>(
+	name: impl Into<String>,
+	i: V,
+	s: T,
+) -> Result<Vec<O>, IterateError<E>>
+where
+	V: Iterator<Item = I>,
+	T: TryTrace<I, O, E>,
+{
+	let mut cache = T::Cache::default();
+	let bar = Bar::new(name, i.size_hint().1.and_then(|v| u64::try_from(v).ok()));
+	let res = i
+		.enumerate()
+		.map(|(index, input)| {
+			let output = s.trace(input, &mut cache);
+			bar.increment(1);
+			output.map_err(|error| IterateError { index, error })
+		})
+		.collect::<Result<Vec<_>, _>>();
+	if res.is_err() {
+		drop(bar.finish_on_drop(false));
+	}
+	res
+}
+
+/// all workers stop at the first error found, which isn't always the one with the lowest index
+#[cfg(feature = "parallel")]
+pub fn iterate_try_parallel<I, O, E, V, T>(
+	name: impl Into<String>,
+	i: V,
+	s: T,
+) -> Result<Vec<O>, IterateError<E>>
+where
+	I: Send,
+	O: Send,
+	E: Send,
+	V: Iterator<Item = I> + Send,
+	T: TryTrace<I, O, E> + Sync,
+	T::Cache: Send,
+{
+	use rayon::iter::{IndexedParallelIterator, IntoParallelIterator, ParallelIterator};
+	let i = i.collect::<Vec<_>>().into_par_iter();
+	let bar = Bar::new(name, u64::try_from(i.len()).ok());
+	let snapshot = Snapshot::capture();
+	let res = i
+		.enumerate()
+		.map_init(
+			|| worker_init::<I, Result<O, E>, T>(&snapshot),
+			|(_, cache), (index, input)| {
+				let output = s.trace(input, cache);
+				bar.increment(1);
+				output.map_err(|error| IterateError { index, error })
+			},
+		)
+		.collect::<Result<Vec<_>, _>>();
+	if res.is_err() {
+		drop(bar.finish_on_drop(false));
+	}
+	res
+}
+
+#[cfg_attr(feature = "inline", inline(always))]
+pub fn iterate_try<I, O, E, V, T>(
+	name: impl Into<String>,
+	i: V,
+	s: T,
+) -> Result<Vec<O>, IterateError<E>>
+where
+	I: Send,
+	O: Send,
+	E: Send,
+	V: Iterator<Item = I> + Send,
+	T: TryTrace<I, O, E> + Sync,
+	T::Cache: Send,
+{
+	#[cfg(feature = "parallel")]
+	return iterate_try_parallel(name, i, s);
+	#[cfg(not(feature = "parallel"))]
+	return iterate_try_linear(name, i, s);
+}
+
+#[cfg(test)]
+mod tests {
+	use super::prelude::*;
+	use super::*;
+	#[test]
+	#[cfg(feature = "parallel")]
+	fn parallel_order() {
+		use rayon::prelude::*;
+		let s = Func(|v: u32| v.wrapping_mul(2654435761));
+		let linear = iterate_linear("linear", 0..100_000, s);
+		let parallel = iterate_parallel("parallel", 0..100_000, s);
+		assert_eq!(linear, parallel);
+		let indexed = iterate_parallel_indexed("indexed", (0..100_000).into_par_iter(), s);
+		assert_eq!(linear, indexed);
+		#[allow(deprecated)]
+		let fast = iterate_parallel_fast("fast", 100_000, (0..100_000).into_par_iter(), s);
+		assert_eq!(linear, fast);
+	}
+	#[test]
+	fn into_chunks() {
+		let s = Func(|v: u32| v * 3);
+		let expected = iterate_linear("collect", 0..10_000, s);
+		assert_eq!(iterate_into("into vec", 0..10_000, s, Vec::new()), expected);
+		let sum = iterate_into(
+			"into fold",
+			0..10_000,
+			s,
+			sink::Fold::new(0u64, |a, v: u32| a + v as u64),
+		);
+		assert_eq!(sum, expected.iter().map(|&v| v as u64).sum());
+		let (tx, rx) = std::sync::mpsc::channel();
+		iterate_into("into channel", 0..10_000, s, tx);
+		assert_eq!(rx.into_iter().flatten().collect::<Vec<_>>(), expected);
+	}
+	#[test]
+	#[cfg(feature = "parallel")]
+	fn into_snapshot() {
+		// changes the parameter between chunks, the chunks after it still see the old value
+		struct Bump(Vec<u64>);
+		impl sink::Sink<u64> for Bump {
+			type Output = Vec<u64>;
+			fn chunk_len(&self) -> usize {
+				10
+			}
+			fn accept(&mut self, mut chunk: Vec<u64>) -> std::ops::ControlFlow<()> {
+				OFFSET.set(OFFSET.get() + 1000);
+				self.0.append(&mut chunk);
+				std::ops::ControlFlow::Continue(())
+			}
+			fn finish(self) -> Self::Output {
+				self.0
+			}
+		}
+		let _guard = OFFSET.set_scoped(0);
+		let s = Func(|v: u64| v + OFFSET.get());
+		let out = iterate_into_parallel("into snapshot", 0..100, s, Bump(Vec::new()));
+		assert_eq!(out, (0..100).collect::<Vec<_>>());
+	}
+	#[test]
+	fn boxed() {
+		let graph: Vec<BoxedTraceSync<u32, u32>> = vec![
+			BoxedTraceSync::new(Func(|v: u32| v + 1)),
+			Func(|v: u32| v * 2).map_out(|v| v + 1).boxed_sync(),
+		];
+		let s = Func(|v: u32| v * 2 + 1);
+		assert_eq!(
+			iterate("boxed", 0..1000, &graph[1]),
+			iterate("direct", 0..1000, s)
+		);
+		assert_eq!(graph[0].trace(1, &mut Default::default()), 2);
+	}
+	#[test]
+	fn branching() {
+		let s = Func(|v: u32| v < 10).select(Func(|v: u32| v / 3), In2Out);
+		assert_eq!(s.trace(9, &mut Default::default()), 3);
+		assert_eq!(s.trace(10, &mut Default::default()), 10);
+		let s = Func(|v: u32| v as usize % 3).switch(
+			[
+				Func(|v: u32| v).boxed(),
+				Func(|v: u32| v * 10).boxed(),
+				Func(|v: u32| v * 100).boxed(),
+			],
+			|&v| v,
+		);
+		let s = s.fanout(In2Out);
+		assert_eq!(
+			iterate_linear("switch", 3..6, s),
+			[(3, 3), (40, 4), (500, 5)]
+		);
+		// keys that aren't indices don't need a conversion to usize
+		#[derive(Clone, Copy)]
+		enum Size {
+			Small,
+			Large,
+		}
+		let s = Func(|v: u32| if v < 10 { Size::Small } else { Size::Large }).switch(
+			[Func(|v: u32| v + 1).boxed(), Func(|v: u32| v - 1).boxed()],
+			|&v| v as usize,
+		);
+		assert_eq!(iterate_linear("switch enum", 9..11, s), [10, 9]);
+	}
+	#[test]
+	fn shared() {
+		use std::sync::atomic::{AtomicU32, Ordering};
+		let calls = AtomicU32::new(0);
+		let node = Func(|v: u32| {
+			calls.fetch_add(1, Ordering::Relaxed);
+			v * 2
+		})
+		.share();
+		let s = node.fanout(node.map_out(|v| v + 1));
+		assert_eq!(iterate_linear("shared", 0..100, s)[10], (20, 21));
+		assert_eq!(calls.load(Ordering::Relaxed), 100);
+		// repeated inputs hit the memo too
+		let inputs = (0..100).flat_map(|v| [v; 3]).collect::<Vec<_>>();
+		assert_eq!(iterate_linear("repeated", inputs.into_iter(), node)[30], 20);
+		assert_eq!(calls.load(Ordering::Relaxed), 200);
+	}
+	#[test]
+	fn try_trace() {
+		let s = Func(|v: u32| v.checked_sub(10).ok_or("underflow"))
+			.map_err(|e| format!("sub: {e}"))
+			.and_then(Func(|v: u32| {
+				100u32.checked_div(v).ok_or("sub: division by zero".into())
+			}));
+		assert_eq!(iterate_try("try ok", 11..14, s), Ok(vec![100, 50, 33]));
+		let err = iterate_try("try err", 10..14, s).unwrap_err();
+		assert_eq!(err.index, 0);
+		assert_eq!(err.error, "sub: division by zero");
+		let err = iterate_try("try err", (10..1000).rev(), s.map_ok(|v| v + 1)).unwrap_err();
+		assert_eq!(err.index, 989);
+	}
+	#[test]
+	fn cancel() {
+		let cancel = cancel::Cancel::new();
+		let s = Func(|v: u32| {
+			if v == 500 {
+				cancel.cancel();
+			}
+			v
+		});
+		let err = iterate_try_linear("cancel", 0..1000, s.cancel_on(&cancel)).unwrap_err();
+		assert_eq!((err.index, err.error), (501, cancel::Cancelled));
+		let res = iterate_into(
+			"cancel into",
+			0..1000,
+			s.cancel_on(&cancel),
+			sink::TrySink::new(Vec::new()),
+		);
+		assert_eq!(res, Err(cancel::Cancelled));
+	}
+	#[test]
+	fn profile() {
+		let inner = Func(|v: u32| v + 1).profile("profile inner");
+		let s = inner.fanout(inner).profile("profile outer");
+		iterate("profile", 0..1000, s);
+		let report = profile::report();
+		let outer = report.0[&vec!["profile outer"]];
+		let inner = report.0[&vec!["profile outer", "profile inner"]];
+		assert_eq!((outer.calls, inner.calls), (1000, 2000));
+		assert!(outer.time >= inner.time);
+	}
+	#[test]
+	fn profile_unwind() {
+		let panics =
+			Func(|v: u32| if v == 0 { panic!("unwind") } else { v }).profile("unwind inner");
+		let res = std::panic::catch_unwind(|| panics.trace(0, &mut Default::default()));
+		assert!(res.is_err());
+		let after = Func(|v: u32| v).profile("unwind after");
+		after.trace(1, &mut Default::default());
+		let report = profile::report();
+		assert_eq!(report.0[&vec!["unwind after"]].calls, 1);
+	}
+	#[test]
+	fn describe() {
+		let s = Func(|v: u32| v + 1)
+			.map_out(|v: u32| v * 2)
+			.fanout(In2Out)
+			.profile("test");
+		let node = s.describe();
+		assert_eq!(node.label, "Profile");
+		assert_eq!(node.children[0].children[0].children[0].label, "Func");
+		let text = node.to_text();
+		assert!(text.starts_with("Profile (name = test)\n  Fanout\n    MapOut (fn = "));
+		assert!(text.ends_with("\n    In2Out\n"));
+		let dot = node.to_dot();
+		assert!(dot.contains("n0 [label=\"Profile\\nname = test\"];"));
+		assert!(dot.contains("n1 -> n4;"));
+	}
+	#[test]
+	fn fold_reduce() {
+		let s = Func(|v: u64| v * v);
+		let sum = iterate_fold("fold", 0..1000, s, || 0, |a, v| a + v, |a, b| a + b);
+		assert_eq!(sum, (0..1000u64).map(|v| v * v).sum());
+		assert_eq!(
+			iterate_reduce("reduce", 0..1000, s, u64::max),
+			Some(999 * 999)
+		);
+		assert_eq!(iterate_reduce("reduce empty", 0..0, s, u64::max), None);
+	}
+
+	parameter_const::define! {
+		copy_parameter OFFSET: u64 = 0;
+		rc_parameter NAME: String = String::from("default");
+		/// only used by the registry test
+		copy_parameter SEED: u32 = 1;
+		global_parameter QUALITY: f32 = 0.5;
+		global_parameter LABEL: String = String::from("global");
+	}
+	mod shadow {
+		parameter_const::define! {
+			/// shares its name with the other `SEED`
+			pub(super) copy_parameter SEED: u32 = 2;
+		}
+	}
+
+	#[test]
+	fn parameters() {
+		let s = Func(|v: u64| v + OFFSET.get());
+		let out = OFFSET.with(100, || iterate("parameters", 0..10_000, s));
+		assert!(out.iter().enumerate().all(|(i, v)| *v == i as u64 + 100));
+		assert_eq!(OFFSET.get(), 0);
+		let sum = OFFSET.with(1, || {
+			iterate_fold("fold", 0..100, s, || 0, |a, v| a + v, |a, b| a + b)
+		});
+		assert_eq!(sum, 100 + 99 * 100 / 2);
+		// outputs don't have to be `Send` to be folded
+		let s = Func(|v: u64| std::rc::Rc::new(v + OFFSET.get()));
+		let sum = iterate_fold("rc", 0..1000, s, || 0, |a, v| a + *v, |a, b| a + b);
+		assert_eq!(sum, 999 * 1000 / 2);
+		// changed in place without a setter, still in the snapshot
+		NAME.get().borrow_mut().push('!');
+		let s = Func(|_: u64| NAME.get().borrow().clone());
+		assert!(iterate("in place", 0..1000, s)
+			.iter()
+			.all(|v| v == "default!"));
+		NAME.set(String::from("default"));
+	}
+
+	#[test]
+	fn parameter_guards() {
+		let res = std::panic::catch_unwind(|| {
+			let _guard = NAME.set_scoped(String::from("scoped"));
+			OFFSET.with(1, || panic!("{}", NAME.get().borrow()))
+		});
+		assert!(res.is_err());
+		assert_eq!(OFFSET.get(), 0);
+		assert_eq!(*NAME.get().borrow(), "default");
+	}
+
+	#[test]
+	fn parameter_registry() {
+		use parameter_const::registry;
+		let path = format!("{}::SEED", module_path!());
+		let entry = registry::find(&path).unwrap();
+		assert_eq!(entry.doc(), "only used by the registry test");
+		assert_eq!(entry.to_string(), format!("{path}: u32 = 1"));
+		assert_eq!(
+			registry::find("SEED").unwrap_err(),
+			registry::OverrideError::Ambiguous {
+				name: String::from("SEED"),
+				candidates: vec![path.clone(), format!("{}::shadow::SEED", module_path!())],
+			}
+		);
+		assert!(registry::set_override("SEED = 7").is_err());
+		registry::set_override(&format!("{path} = 7")).unwrap();
+		assert_eq!(SEED.get(), 7);
+		assert_eq!(shadow::SEED.get(), 2);
+		assert_eq!(std::thread::spawn(|| SEED.get()).join().unwrap(), 7);
+		assert!(registry::set_override(&format!("{path}=x")).is_err());
+		assert_eq!(
+			registry::set_override("MISSING=1"),
+			Err(registry::OverrideError::Unknown(String::from("MISSING")))
+		);
+		// the override is undone for this thread when the scope ends
+		SEED.with(5, || registry::set_override(&format!("{path}=9")).unwrap());
+		assert_eq!(SEED.get(), 7);
+		assert!(registry::parameters().any(|v| v.name() == "NAME"));
+	}
+
+	#[test]
+	fn parameter_combinators() {
+		let inner = read_param(&OFFSET).fanout(Func(|v: u64| v + OFFSET.get()));
+		let s = with_param(&OFFSET, Func(|v: u64| v * 2), inner);
+		assert_eq!(
+			iterate_linear("with param", 1..4, s),
+			[(2, 3), (4, 6), (6, 9)]
+		);
+		assert_eq!(OFFSET.get(), 0);
+		assert_eq!(
+			s.describe().to_text().lines().next(),
+			Some("WithParam (param = OFFSET)")
+		);
+	}
+
+	#[test]
+	fn global_parameters() {
+		let s = Func(|v: u32| v as f32 * QUALITY.get());
+		let out = QUALITY.with(2.0, || iterate_linear("global", 0..4, s));
+		assert_eq!(out, [0.0, 2.0, 4.0, 6.0]);
+		assert_eq!(QUALITY.get(), 0.5);
+		// small `Copy` types are atomics in the block form too
+		use parameter_const::{global, GlobalParameter};
+		let _: &GlobalParameter<f32, global::Atomic<f32>> = &QUALITY;
+		let _: &GlobalParameter<String, global::Lock<String>> = &LABEL;
+		let _guard = LABEL.set_scoped(String::from("scoped"));
+		assert_eq!(std::thread::spawn(|| LABEL.get()).join().unwrap(), "scoped");
+		parameter_const::registry::set_override("LABEL=set").unwrap();
+		assert_eq!(LABEL.get(), "set");
+	}
+}
@@ -0,0 +1,88 @@
+//! reading and setting [`parameter_const`] parameters from inside a graph
+
+use parameter_const::Parameter;
+
+use crate::describe::{Describe, Node};
+use crate::Trace;
+
+/// evaluates `inner` with `param` set to the output of `value`, both get the same input
+#[cfg_attr(feature = "inline", inline(always))]
+pub fn with_param<P: Parameter + 'static, V, T>(
+	param: &'static P,
+	value: V,
+	inner: T,
+) -> WithParam<P, V, T> {
+	WithParam {
+		param,
+		value,
+		inner,
+	}
+}
+
+/// the value of `param` on the current thread, whatever the input
+#[cfg_attr(feature = "inline", inline(always))]
+pub fn read_param<P: Parameter + 'static>(param: &'static P) -> ReadParam<P> {
+	ReadParam { param }
+}
+
+#[derive(Debug)]
+pub struct WithParam<P: 'static, V, T> {
+	param: &'static P,
+	value: V,
+	inner: T,
+}
+impl<P, V: Clone, T: Clone> Clone for WithParam<P, V, T> {
+	fn clone(&self) -> Self {
+		Self {
+			param: self.param,
+			value: self.value.clone(),
+			inner: self.inner.clone(),
+		}
+	}
+}
+impl<P, V: Copy, T: Copy> Copy for WithParam<P, V, T> {}
+impl<I: Clone, O, P, V, T> Trace<I, O> for WithParam<P, V, T>
+where
+	P: Parameter,
+	V: Trace<I, P::Value>,
+	T: Trace<I, O>,
+{
+	type Cache = (V::Cache, T::Cache);
+	#[cfg_attr(feature = "inline", inline(always))]
+	fn trace(&self, input: I, cache: &mut Self::Cache) -> O {
+		let value = self.value.trace(input.clone(), &mut cache.0);
+		let _guard = self.param.set_scoped(value);
+		self.inner.trace(input, &mut cache.1)
+	}
+}
+impl<P: Parameter, V: Describe, T: Describe> Describe for WithParam<P, V, T> {
+	fn describe(&self) -> Node {
+		Node::new("WithParam")
+			.param("param", self.param.name())
+			.child(self.value.describe())
+			.child(self.inner.describe())
+	}
+}
+
+#[derive(Debug)]
+pub struct ReadParam<P: 'static> {
+	param: &'static P,
+}
+impl<P> Clone for ReadParam<P> {
+	fn clone(&self) -> Self {
+		*self
+	}
+}
+impl<P> Copy for ReadParam<P> {}
+impl<I, P: Parameter> Trace<I, P::Value> for ReadParam<P> {
+	type Cache = ();
+	#[cfg_attr(feature = "inline", inline(always))]
+	fn trace(&self, _input: I, _cache: &mut Self::Cache) -> P::Value {
+		self.param.read()
+	}
+}
+impl<P: Parameter> Describe for ReadParam<P> {
+	fn describe(&self) -> Node {
+		Node::new("ReadParam").param("param", self.param.name())
+	}
+}
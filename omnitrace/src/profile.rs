@@ -0,0 +1,143 @@
+//! per-node call counts and timings, see [`TraceExtCore::profile`](crate::ext::TraceExtCore::profile)
+
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::describe::{Describe, Node};
+use crate::Trace;
+
+thread_local! {
+	/// names of the profiled nodes currently being traced on this thread
+	static STACK: RefCell<Vec<&'static str>> = const { RefCell::new(Vec::new()) };
+}
+
+/// counters of every finished cache, merged by path
+static REGISTRY: Mutex<BTreeMap<Vec<&'static str>, Counter>> = Mutex::new(BTreeMap::new());
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Counter {
+	pub calls: u64,
+	/// wall time summed over all workers, children included
+	pub time: Duration,
+}
+impl Counter {
+	fn merge(&mut self, other: Self) {
+		self.calls += other.calls;
+		self.time += other.time;
+	}
+}
+
+/// counters live in the cache so tracing never locks, they get merged into the report when it's dropped
+#[derive(Debug, Default)]
+pub struct ProfileCache<C> {
+	inner: C,
+	counters: Vec<(Vec<&'static str>, Counter)>,
+}
+impl<C> Drop for ProfileCache<C> {
+	fn drop(&mut self) {
+		if self.counters.is_empty() {
+			return;
+		}
+		let mut registry = REGISTRY.lock().unwrap_or_else(|e| e.into_inner());
+		for (path, counter) in self.counters.drain(..) {
+			registry.entry(path).or_default().merge(counter);
+		}
+	}
+}
+
+/// pops the name pushed by [`Profile::trace`] even if tracing panics, so a caught panic
+/// doesn't leave the thread's later paths nested under it
+struct PopOnDrop;
+impl Drop for PopOnDrop {
+	fn drop(&mut self) {
+		STACK.with(|stack| stack.borrow_mut().pop());
+	}
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Profile<T> {
+	pub(crate) parent: T,
+	pub(crate) name: &'static str,
+}
+impl<I, O, T: Trace<I, O>> Trace<I, O> for Profile<T> {
+	type Cache = ProfileCache<T::Cache>;
+	fn trace(&self, input: I, cache: &mut Self::Cache) -> O {
+		// the path is usually the same every call, so only allocate for new ones
+		let index = STACK.with(|stack| {
+			let mut stack = stack.borrow_mut();
+			stack.push(self.name);
+			match cache.counters.iter().position(|(path, _)| *path == *stack) {
+				Some(index) => index,
+				None => {
+					cache.counters.push((stack.clone(), Counter::default()));
+					cache.counters.len() - 1
+				}
+			}
+		});
+		let pop = PopOnDrop;
+		let start = Instant::now();
+		let output = self.parent.trace(input, &mut cache.inner);
+		let time = start.elapsed();
+		drop(pop);
+		cache.counters[index].1.merge(Counter { calls: 1, time });
+		output
+	}
+}
+
+impl<T: Describe> Describe for Profile<T> {
+	fn describe(&self) -> Node {
+		Node::new("Profile")
+			.param("name", self.name)
+			.child(self.parent.describe())
+	}
+}
+
+/// hierarchical counters of every profiled node, `Display` prints them as a tree
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Report(pub BTreeMap<Vec<&'static str>, Counter>);
+impl Report {
+	/// time spent in a node minus the time spent in its direct children
+	pub fn self_time(&self, path: &[&'static str]) -> Duration {
+		let children = self
+			.0
+			.iter()
+			.filter(|(child, _)| child.len() == path.len() + 1 && child.starts_with(path))
+			.map(|(_, counter)| counter.time)
+			.sum();
+		self.0
+			.get(path)
+			.map_or(Duration::ZERO, |v| v.time.saturating_sub(children))
+	}
+}
+impl fmt::Display for Report {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		for (path, counter) in &self.0 {
+			writeln!(
+				f,
+				"{:indent$}{}\t{} calls\t{:?} ({:?} self)",
+				"",
+				path.last().unwrap_or(&""),
+				counter.calls,
+				counter.time,
+				self.self_time(path),
+				indent = 2 * path.len().saturating_sub(1),
+			)?;
+		}
+		Ok(())
+	}
+}
+
+/// counters merged so far, only finished caches are included (so call after `iterate` returns)
+pub fn report() -> Report {
+	Report(REGISTRY.lock().unwrap_or_else(|e| e.into_inner()).clone())
+}
+
+/// like [`report`] but also resets the counters
+pub fn take_report() -> Report {
+	Report(std::mem::take(
+		&mut *REGISTRY.lock().unwrap_or_else(|e| e.into_inner()),
+	))
+}
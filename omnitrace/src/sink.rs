@@ -0,0 +1,115 @@
+//! output consumers for [`crate::iterate_into`]
+
+use std::ops::ControlFlow;
+use std::sync::mpsc;
+
+/// receives outputs in input order, one chunk at a time
+pub trait Sink<O> {
+	type Output;
+	/// preferred number of outputs per chunk, the last chunk may be shorter
+	#[cfg_attr(feature = "inline", inline(always))]
+	fn chunk_len(&self) -> usize {
+		4096
+	}
+	/// return [`ControlFlow::Break`] to stop iterating early
+	fn accept(&mut self, chunk: Vec<O>) -> ControlFlow<()>;
+	fn finish(self) -> Self::Output;
+}
+
+impl<O> Sink<O> for Vec<O> {
+	type Output = Self;
+	fn accept(&mut self, mut chunk: Vec<O>) -> ControlFlow<()> {
+		self.append(&mut chunk);
+		ControlFlow::Continue(())
+	}
+	fn finish(self) -> Self::Output {
+		self
+	}
+}
+
+/// sequential fold over all outputs, see [`crate::iterate_fold`] for a per-worker one
+#[derive(Debug, Clone, Copy)]
+pub struct Fold<A, F> {
+	acc: Option<A>,
+	func: F,
+}
+impl<A, F> Fold<A, F> {
+	pub fn new<O>(init: A, func: F) -> Self
+	where
+		F: FnMut(A, O) -> A,
+	{
+		Self {
+			acc: Some(init),
+			func,
+		}
+	}
+}
+impl<O, A, F: FnMut(A, O) -> A> Sink<O> for Fold<A, F> {
+	type Output = A;
+	fn accept(&mut self, chunk: Vec<O>) -> ControlFlow<()> {
+		self.acc = self
+			.acc
+			.take()
+			.map(|acc| chunk.into_iter().fold(acc, &mut self.func));
+		ControlFlow::Continue(())
+	}
+	fn finish(self) -> Self::Output {
+		self.acc.unwrap()
+	}
+}
+
+// stop once the receiver is gone, there's nobody left to compute for
+impl<O> Sink<O> for mpsc::Sender<Vec<O>> {
+	type Output = ();
+	fn accept(&mut self, chunk: Vec<O>) -> ControlFlow<()> {
+		match self.send(chunk) {
+			Ok(()) => ControlFlow::Continue(()),
+			Err(_) => ControlFlow::Break(()),
+		}
+	}
+	fn finish(self) -> Self::Output {}
+}
+impl<O> Sink<O> for mpsc::SyncSender<Vec<O>> {
+	type Output = ();
+	fn accept(&mut self, chunk: Vec<O>) -> ControlFlow<()> {
+		match self.send(chunk) {
+			Ok(()) => ControlFlow::Continue(()),
+			Err(_) => ControlFlow::Break(()),
+		}
+	}
+	fn finish(self) -> Self::Output {}
+}
+
+/// forwards successful outputs, stops at the first error
+#[derive(Debug, Clone, Copy)]
+pub struct TrySink<S, E> {
+	sink: S,
+	error: Option<E>,
+}
+impl<S, E> TrySink<S, E> {
+	pub fn new(sink: S) -> Self {
+		Self { sink, error: None }
+	}
+}
+impl<O, E, S: Sink<O>> Sink<Result<O, E>> for TrySink<S, E> {
+	type Output = Result<S::Output, E>;
+	#[cfg_attr(feature = "inline", inline(always))]
+	fn chunk_len(&self) -> usize {
+		self.sink.chunk_len()
+	}
+	fn accept(&mut self, chunk: Vec<Result<O, E>>) -> ControlFlow<()> {
+		match chunk.into_iter().collect() {
+			Ok(chunk) => self.sink.accept(chunk),
+			Err(e) => {
+				self.error = Some(e);
+				ControlFlow::Break(())
+			}
+		}
+	}
+	fn finish(self) -> Self::Output {
+		match self.error {
+			Some(e) => Err(e),
+			None => Ok(self.sink.finish()),
+		}
+	}
+}
@@ -0,0 +1,170 @@
+//! process-wide parameters, see `global_parameter` in [`crate::define!`]
+
+use std::marker::PhantomData;
+use std::sync::atomic::{self, Ordering};
+use std::sync::{LazyLock, RwLock};
+
+use crate::Parameter;
+
+/// backing of a [`GlobalParameter`]
+pub trait Storage<T>: Sync {
+	fn new(v: T) -> Self;
+	fn load(&self) -> T;
+	fn swap(&self, v: T) -> T;
+}
+
+/// used for everything that isn't a primitive written out by name
+#[derive(Debug)]
+pub struct Lock<T>(RwLock<T>);
+impl<T: Clone + Send + Sync> Storage<T> for Lock<T> {
+	fn new(v: T) -> Self {
+		Self(RwLock::new(v))
+	}
+	fn load(&self) -> T {
+		self.0.read().unwrap_or_else(|e| e.into_inner()).clone()
+	}
+	fn swap(&self, v: T) -> T {
+		std::mem::replace(&mut *self.0.write().unwrap_or_else(|e| e.into_inner()), v)
+	}
+}
+
+/// used for `bool`, integers and floats
+#[derive(Debug)]
+pub struct Atomic<T: AtomicValue>(T::Atomic);
+impl<T: AtomicValue> Storage<T> for Atomic<T> {
+	fn new(v: T) -> Self {
+		Self(T::new(v))
+	}
+	fn load(&self) -> T {
+		T::load(&self.0)
+	}
+	fn swap(&self, v: T) -> T {
+		T::swap(&self.0, v)
+	}
+}
+
+pub trait AtomicValue: Copy {
+	type Atomic: Sync + std::fmt::Debug;
+	fn new(v: Self) -> Self::Atomic;
+	fn load(a: &Self::Atomic) -> Self;
+	fn swap(a: &Self::Atomic, v: Self) -> Self;
+}
+
+macro_rules! impl_atomic_value {
+	($($ty:ident $atomic:ident $to:expr, $from:expr;)*) => {$(
+		impl AtomicValue for $ty {
+			type Atomic = atomic::$atomic;
+			fn new(v: Self) -> Self::Atomic {
+				atomic::$atomic::new($to(v))
+			}
+			fn load(a: &Self::Atomic) -> Self {
+				$from(a.load(Ordering::Relaxed))
+			}
+			fn swap(a: &Self::Atomic, v: Self) -> Self {
+				$from(a.swap($to(v), Ordering::Relaxed))
+			}
+		}
+	)*};
+}
+
+impl_atomic_value! {
+	bool AtomicBool bool::from, bool::from;
+	u8 AtomicU8 u8::from, u8::from;
+	u16 AtomicU16 u16::from, u16::from;
+	u32 AtomicU32 u32::from, u32::from;
+	u64 AtomicU64 u64::from, u64::from;
+	usize AtomicUsize usize::from, usize::from;
+	i8 AtomicI8 i8::from, i8::from;
+	i16 AtomicI16 i16::from, i16::from;
+	i32 AtomicI32 i32::from, i32::from;
+	i64 AtomicI64 i64::from, i64::from;
+	isize AtomicIsize isize::from, isize::from;
+	f32 AtomicU32 f32::to_bits, f32::from_bits;
+	f64 AtomicU64 f64::to_bits, f64::from_bits;
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __global_storage {
+	(bool) => { $crate::global::Atomic<bool> };
+	(u8) => { $crate::global::Atomic<u8> };
+	(u16) => { $crate::global::Atomic<u16> };
+	(u32) => { $crate::global::Atomic<u32> };
+	(u64) => { $crate::global::Atomic<u64> };
+	(usize) => { $crate::global::Atomic<usize> };
+	(i8) => { $crate::global::Atomic<i8> };
+	(i16) => { $crate::global::Atomic<i16> };
+	(i32) => { $crate::global::Atomic<i32> };
+	(i64) => { $crate::global::Atomic<i64> };
+	(isize) => { $crate::global::Atomic<isize> };
+	(f32) => { $crate::global::Atomic<f32> };
+	(f64) => { $crate::global::Atomic<f64> };
+	($ty:ty) => { $crate::global::Lock<$ty> };
+}
+
+/// a parameter shared by every thread
+#[derive(Debug)]
+pub struct GlobalParameter<T, S: 'static> {
+	pub(crate) name: &'static str,
+	pub(crate) storage: &'static LazyLock<S>,
+	pub(crate) _value: PhantomData<fn() -> T>,
+}
+
+impl<T, S: Storage<T>> GlobalParameter<T, S> {
+	pub fn name(&self) -> &'static str {
+		self.name
+	}
+	pub fn get(&self) -> T {
+		self.storage.load()
+	}
+	/// sets the value for every thread until it's set again, prefer [`Self::with`] or [`Self::set_scoped`]
+	pub fn set(&self, v: T) {
+		drop(self.storage.swap(v));
+	}
+	/// the old value is restored when the guard is dropped, even when unwinding
+	pub fn set_scoped(&self, v: T) -> GlobalGuard<'_, T, S> {
+		GlobalGuard {
+			old: Some(self.storage.swap(v)),
+			param: self,
+		}
+	}
+	/// unlike thread-local parameters, every thread sees `v` while `f` runs, not only the current one.
+	/// overlapping calls from different threads restore whatever value they replaced when they return,
+	/// so the last one to return wins, nest them on a single thread only
+	pub fn with<R, F: FnOnce() -> R>(&self, v: T, f: F) -> R {
+		let _guard = self.set_scoped(v);
+		f()
+	}
+}
+
+impl<T, S: Storage<T>> Parameter for GlobalParameter<T, S> {
+	type Value = T;
+	type Guard<'a>
+		= GlobalGuard<'a, T, S>
+	where
+		Self: 'a;
+	fn name(&self) -> &'static str {
+		self.name
+	}
+	fn read(&self) -> T {
+		self.get()
+	}
+	fn set_scoped(&self, v: T) -> GlobalGuard<'_, T, S> {
+		self.set_scoped(v)
+	}
+}
+
+/// restores the previous value of a [`GlobalParameter`] when dropped
+#[must_use]
+#[derive(Debug)]
+pub struct GlobalGuard<'a, T, S: Storage<T> + 'static> {
+	param: &'a GlobalParameter<T, S>,
+	old: Option<T>,
+}
+impl<T, S: Storage<T>> Drop for GlobalGuard<'_, T, S> {
+	fn drop(&mut self) {
+		if let Some(old) = self.old.take() {
+			self.param.set(old);
+		}
+	}
+}
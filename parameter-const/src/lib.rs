@@ -2,78 +2,419 @@ use std::cell::{Cell, RefCell};
 use std::rc::Rc;
 use std::thread::LocalKey;
 
+use snapshot::{Capture, Captured, Install, Restore};
+
+pub mod global;
+pub mod registry;
+pub mod snapshot;
+
+pub use global::GlobalParameter;
+
 pub mod __ {
 	pub use paste::paste;
 	pub use std::cell::{Cell, RefCell};
 	pub use std::rc::Rc;
+	pub use std::sync::LazyLock;
 	use std::thread::LocalKey;
-	pub const fn rc_param<T>(k: &'static LocalKey<Rc<RefCell<T>>>) -> crate::RcParameter<T> {
-		crate::RcParameter { k }
+
+	pub use crate::registry::PARAMETERS;
+	pub use linkme;
+	use std::fmt::Display;
+	use std::marker::PhantomData;
+	use std::str::FromStr;
+
+	use crate::registry::Entry;
+	use crate::snapshot::Capture;
+	pub const fn rc_param<T>(
+		name: &'static str,
+		k: &'static LocalKey<Rc<RefCell<T>>>,
+	) -> crate::RcParameter<T> {
+		crate::RcParameter { name, k }
+	}
+	pub const fn copy_param<T: Copy>(
+		name: &'static str,
+		k: &'static LocalKey<Cell<T>>,
+	) -> crate::CopyParameter<T> {
+		crate::CopyParameter { name, k }
+	}
+
+	pub const fn global_param<T, S>(
+		name: &'static str,
+		storage: &'static LazyLock<S>,
+	) -> crate::GlobalParameter<T, S> {
+		crate::GlobalParameter {
+			name,
+			storage,
+			_value: PhantomData,
+		}
+	}
+	pub const fn entry(
+		name: &'static str,
+		module: &'static str,
+		type_name: &'static str,
+		doc: &'static str,
+		read: fn() -> Option<String>,
+		set: fn(&str) -> Option<Result<(), String>>,
+		capture: fn() -> Option<&'static dyn Capture>,
+	) -> Entry {
+		Entry {
+			name,
+			module,
+			type_name,
+			doc,
+			read,
+			set,
+			capture,
+		}
+	}
+	pub fn initial<T>(
+		entry: &'static Entry,
+		parse: fn(&str) -> Option<Result<T, String>>,
+		init: impl FnOnce() -> T,
+	) -> T {
+		entry.initial(parse, init)
 	}
-	pub const fn copy_param<T: Copy>(k: &'static LocalKey<Cell<T>>) -> crate::CopyParameter<T> {
-		crate::CopyParameter { k }
+
+	// autoref specialization, only parameters that implement `Capture` get an entry
+	pub struct Probe<P: 'static>(pub &'static P);
+	pub trait ViaCapture {
+		fn capture_entry(&self) -> Option<&'static dyn Capture>;
+	}
+	impl<P: Capture> ViaCapture for Probe<P> {
+		fn capture_entry(&self) -> Option<&'static dyn Capture> {
+			Some(self.0)
+		}
+	}
+	pub trait ViaNone {
+		fn capture_entry(&self) -> Option<&'static dyn Capture> {
+			None
+		}
+	}
+	impl<P> ViaNone for &Probe<P> {}
+
+	// same for reading and parsing values, call on `&&&ValueProbe` to go through them in the order below
+	pub struct ValueProbe<T>(PhantomData<fn() -> T>);
+	pub const fn value_probe<T>() -> ValueProbe<T> {
+		ValueProbe(PhantomData)
+	}
+	pub trait ViaStr<T> {
+		fn display(&self, v: &T) -> Option<String>;
+		fn parse(&self, s: &str) -> Option<Result<T, String>>;
+	}
+	impl<T: Display + FromStr> ViaStr<T> for &&ValueProbe<T>
+	where
+		T::Err: Display,
+	{
+		fn display(&self, v: &T) -> Option<String> {
+			Some(v.to_string())
+		}
+		fn parse(&self, s: &str) -> Option<Result<T, String>> {
+			Some(s.parse().map_err(|e: T::Err| e.to_string()))
+		}
 	}
+	pub trait ViaDisplay<T> {
+		fn display(&self, v: &T) -> Option<String>;
+		fn parse(&self, _: &str) -> Option<Result<T, String>> {
+			None
+		}
+	}
+	impl<T: Display> ViaDisplay<T> for &&&ValueProbe<T> {
+		fn display(&self, v: &T) -> Option<String> {
+			Some(v.to_string())
+		}
+	}
+	pub trait ViaDebug<T> {
+		fn display(&self, v: &T) -> Option<String>;
+		fn parse(&self, _: &str) -> Option<Result<T, String>> {
+			None
+		}
+	}
+	impl<T: std::fmt::Debug> ViaDebug<T> for &ValueProbe<T> {
+		fn display(&self, v: &T) -> Option<String> {
+			Some(format!("{v:?}"))
+		}
+	}
+	pub trait ViaOpaque<T> {
+		fn display(&self, _: &T) -> Option<String> {
+			None
+		}
+		fn parse(&self, _: &str) -> Option<Result<T, String>> {
+			None
+		}
+	}
+	impl<T> ViaOpaque<T> for ValueProbe<T> {}
+}
+
+/// common interface of [`RcParameter`] and [`CopyParameter`]
+pub trait Parameter {
+	type Value;
+	type Guard<'a>
+	where
+		Self: 'a;
+	fn name(&self) -> &'static str;
+	/// copy of the value on the current thread
+	fn read(&self) -> Self::Value;
+	fn set_scoped(&self, v: Self::Value) -> Self::Guard<'_>;
 }
 
 #[derive(Debug)]
 pub struct RcParameter<T: 'static> {
+	pub(crate) name: &'static str,
 	pub(crate) k: &'static LocalKey<Rc<RefCell<T>>>,
 }
 
 impl<T> RcParameter<T> {
+	pub fn name(&self) -> &'static str {
+		self.name
+	}
 	pub fn get(&self) -> Rc<RefCell<T>> {
 		self.k.with(|v| v.clone())
 	}
+	/// sets the value on the current thread until it's set again, prefer [`Self::with`] or [`Self::set_scoped`]
+	pub fn set(&self, v: T) {
+		drop(self.replace(v));
+	}
+	/// the old value is restored when the guard is dropped, even when unwinding
+	pub fn set_scoped(&self, v: T) -> RcGuard<'_, T> {
+		RcGuard {
+			old: Some(self.replace(v)),
+			param: self,
+		}
+	}
+	/// only the current thread sees `v`, except for the workers of omnitrace's `iterate` functions,
+	/// which start with a [`snapshot`] of the calling thread
 	pub fn with<R, F: FnOnce() -> R>(&self, v: T, f: F) -> R {
-		let old = std::mem::replace(&mut *self.get().borrow_mut(), v);
-		let res = f();
-		drop(std::mem::replace(&mut *self.get().borrow_mut(), old));
-		res
+		let _guard = self.set_scoped(v);
+		f()
+	}
+	fn replace(&self, v: T) -> T {
+		std::mem::replace(&mut *self.get().borrow_mut(), v)
+	}
+}
+impl<T: Clone> Parameter for RcParameter<T> {
+	type Value = T;
+	type Guard<'a> = RcGuard<'a, T>;
+	fn name(&self) -> &'static str {
+		self.name
+	}
+	fn read(&self) -> T {
+		T::clone(&self.get().borrow())
+	}
+	fn set_scoped(&self, v: T) -> RcGuard<'_, T> {
+		self.set_scoped(v)
+	}
+}
+impl<T: Clone + Send + Sync + 'static> Capture for RcParameter<T> {
+	fn capture(&'static self) -> Box<dyn Install> {
+		Box::new(Captured {
+			param: self,
+			value: T::clone(&self.get().borrow()),
+		})
 	}
 }
+impl<T: Clone + Send + Sync + 'static> Install for Captured<RcParameter<T>, T> {
+	fn install(&self) -> Box<dyn Restore> {
+		Box::new(self.param.set_scoped(self.value.clone()))
+	}
+}
+
+/// restores the previous value of an [`RcParameter`] when dropped
+#[must_use]
+#[derive(Debug)]
+pub struct RcGuard<'a, T: 'static> {
+	param: &'a RcParameter<T>,
+	old: Option<T>,
+}
+impl<T> Drop for RcGuard<'_, T> {
+	fn drop(&mut self) {
+		if let Some(old) = self.old.take() {
+			self.param.replace(old);
+		}
+	}
+}
+impl<T> Restore for RcGuard<'_, T> {}
 
 #[derive(Debug)]
 pub struct CopyParameter<T: 'static + Copy> {
+	pub(crate) name: &'static str,
 	pub(crate) k: &'static LocalKey<Cell<T>>,
 }
 
 impl<T: Copy> CopyParameter<T> {
+	pub fn name(&self) -> &'static str {
+		self.name
+	}
 	pub fn get(&self) -> T {
 		self.k.with(|v| v.get())
 	}
+	/// sets the value on the current thread until it's set again, prefer [`Self::with`] or [`Self::set_scoped`]
+	pub fn set(&self, v: T) {
+		self.replace(v);
+	}
+	/// the old value is restored when the guard is dropped, even when unwinding
+	pub fn set_scoped(&self, v: T) -> CopyGuard<'_, T> {
+		CopyGuard {
+			old: self.replace(v),
+			param: self,
+		}
+	}
+	/// only the current thread sees `v`, except for the workers of omnitrace's `iterate` functions,
+	/// which start with a [`snapshot`] of the calling thread
 	pub fn with<R, F: FnOnce() -> R>(&self, v: T, f: F) -> R {
-		let old = self.k.with(|o| o.replace(v));
-		let res = f();
-		self.k.with(|o| o.set(old));
-		res
+		let _guard = self.set_scoped(v);
+		f()
+	}
+	fn replace(&self, v: T) -> T {
+		self.k.with(|o| o.replace(v))
+	}
+}
+
+impl<T: Copy> Parameter for CopyParameter<T> {
+	type Value = T;
+	type Guard<'a> = CopyGuard<'a, T>;
+	fn name(&self) -> &'static str {
+		self.name
+	}
+	fn read(&self) -> T {
+		self.get()
+	}
+	fn set_scoped(&self, v: T) -> CopyGuard<'_, T> {
+		self.set_scoped(v)
+	}
+}
+impl<T: Copy + Send + Sync + 'static> Capture for CopyParameter<T> {
+	fn capture(&'static self) -> Box<dyn Install> {
+		Box::new(Captured {
+			param: self,
+			value: self.get(),
+		})
+	}
+}
+impl<T: Copy + Send + Sync + 'static> Install for Captured<CopyParameter<T>, T> {
+	fn install(&self) -> Box<dyn Restore> {
+		Box::new(self.param.set_scoped(self.value))
+	}
+}
+
+/// restores the previous value of a [`CopyParameter`] when dropped
+#[must_use]
+#[derive(Debug)]
+pub struct CopyGuard<'a, T: 'static + Copy> {
+	param: &'a CopyParameter<T>,
+	old: T,
+}
+impl<T: Copy> Drop for CopyGuard<'_, T> {
+	fn drop(&mut self) {
+		self.param.replace(self.old);
 	}
 }
+impl<T: Copy> Restore for CopyGuard<'_, T> {}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __doc {
+	([$($acc:literal)*]) => {
+		concat!($($acc, "\n"),*)
+	};
+	([$($acc:literal)*] #[doc = $doc:literal] $($rest:tt)*) => {
+		$crate::__doc!([$($acc)* $doc] $($rest)*)
+	};
+	([$($acc:literal)*] #[$($other:tt)*] $($rest:tt)*) => {
+		$crate::__doc!([$($acc)*] $($rest)*)
+	};
+}
 
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __entry {
+	($(#[$($meta:tt)*])* $id:ident: $ty:ty, $read:expr, $capture:expr) => {
+		$crate::__::paste! {
+			#[allow(non_snake_case)]
+			fn [<__parse_ $id>](s: &str) -> Option<Result<$ty, String>> {
+				#[allow(unused_imports)]
+				use $crate::__::{ViaDebug, ViaDisplay, ViaOpaque, ViaStr};
+				(&&&$crate::__::value_probe::<$ty>()).parse(s)
+			}
+			#[$crate::__::linkme::distributed_slice($crate::__::PARAMETERS)]
+			#[linkme(crate = $crate::__::linkme)]
+			static [<__ENTRY_ $id>]: $crate::registry::Entry = $crate::__::entry(
+				stringify!($id),
+				module_path!(),
+				stringify!($ty),
+				$crate::__doc!([] $(#[$($meta)*])*),
+				|| {
+					#[allow(unused_imports)]
+					use $crate::__::{ViaDebug, ViaDisplay, ViaOpaque, ViaStr};
+					(&&&$crate::__::value_probe::<$ty>()).display($read)
+				},
+				|s| [<__parse_ $id>](s).map(|v| v.map(|v| $id.set(v))),
+				$capture,
+			);
+		}
+	};
+}
+
+/// `rc_parameter` and `copy_parameter` are thread-local, `global_parameter` is shared by all threads
 #[macro_export]
 macro_rules! define {
-	($(#[$meta:meta])* $vis:vis rc_parameter $id:ident: $ty:ty = $init:expr) => {
+	(@global $(#[$($meta:tt)*])* $vis:vis $id:ident: $ty:ty = $init:expr; $storage:ty) => {
+		$crate::__::paste! {
+			static [<__INNER_ $id>]: $crate::__::LazyLock<$storage> = $crate::__::LazyLock::new(|| {
+				$crate::global::Storage::new($crate::__::initial(&[<__ENTRY_ $id>], [<__parse_ $id>], || $init))
+			});
+			// every thread already sees the same value
+			$crate::__entry!($(#[$($meta)*])* $id: $ty, &$id.get(), || None);
+			$(#[$($meta)*])*
+			$vis static $id: $crate::GlobalParameter<$ty, $storage> = $crate::__::global_param(stringify!($id), &[<__INNER_ $id>]);
+		}
+	};
+	($(#[$($meta:tt)*])* $vis:vis global_parameter $id:ident: $ty:ident = $init:expr) => {
+		$crate::define!(@global $(#[$($meta)*])* $vis $id: $ty = $init; $crate::__global_storage!($ty));
+	};
+	($(#[$($meta:tt)*])* $vis:vis global_parameter $id:ident: $ty:ty = $init:expr) => {
+		$crate::define!(@global $(#[$($meta)*])* $vis $id: $ty = $init; $crate::global::Lock<$ty>);
+	};
+	($(#[$($meta:tt)*])* $vis:vis rc_parameter $id:ident: $ty:ty = $init:expr) => {
 		$crate::__::paste! {
 			thread_local! {
-				static [<__INNER_ $id>]: $crate::__::Rc<$crate::__::RefCell<$ty>> = $crate::__::Rc::new($crate::__::RefCell::new($init));
+				static [<__INNER_ $id>]: $crate::__::Rc<$crate::__::RefCell<$ty>> = $crate::__::Rc::new($crate::__::RefCell::new(
+					$crate::__::initial(&[<__ENTRY_ $id>], [<__parse_ $id>], || $init)
+				));
 			}
-			$(#[$meta])*
-			$vis static $id: $crate::Parameter<$ty> = $crate::__::rc_param(&[<__INNER_ $id>]);
+			$crate::__entry!($(#[$($meta)*])* $id: $ty, &*$id.get().borrow(), || {
+				#[allow(unused_imports)]
+				use $crate::__::{ViaCapture, ViaNone};
+				(&$crate::__::Probe(&$id)).capture_entry()
+			});
+			$(#[$($meta)*])*
+			$vis static $id: $crate::RcParameter<$ty> = $crate::__::rc_param(stringify!($id), &[<__INNER_ $id>]);
 		}
 	};
-	($(#[$meta:meta])* $vis:vis copy_parameter $id:ident: $ty:ty = $init:expr) => {
+	($(#[$($meta:tt)*])* $vis:vis copy_parameter $id:ident: $ty:ty = $init:expr) => {
 		$crate::__::paste! {
 			thread_local! {
-				static [<__INNER_ $id>]: $crate::__::Cell<$ty> = $crate::__::Cell::new($init);
+				static [<__INNER_ $id>]: $crate::__::Cell<$ty> = $crate::__::Cell::new(
+					$crate::__::initial(&[<__ENTRY_ $id>], [<__parse_ $id>], || $init)
+				);
 			}
-			$(#[$meta])*
-			$vis static $id: $crate::CopyParameter<$ty> = $crate::__::copy_param(&[<__INNER_ $id>]);
+			$crate::__entry!($(#[$($meta)*])* $id: $ty, &$id.get(), || {
+				#[allow(unused_imports)]
+				use $crate::__::{ViaCapture, ViaNone};
+				(&$crate::__::Probe(&$id)).capture_entry()
+			});
+			$(#[$($meta)*])*
+			$vis static $id: $crate::CopyParameter<$ty> = $crate::__::copy_param(stringify!($id), &[<__INNER_ $id>]);
 		}
 	};
-	($(
-		$(#[$meta:meta])* $vis:vis $mode:ident $id:ident: $ty:ty = $init:expr;
-	)*) => {
-		$($crate::define!(
-			$(#[$meta])* $vis $mode $id: $ty = $init
-		);)*
+	// one item at a time, a type that's a single ident is passed on as tokens, a `ty` fragment
+	// wouldn't match `$ty:ident` anymore and global parameters would never pick an atomic
+	() => {};
+	($(#[$($meta:tt)*])* $vis:vis $mode:ident $id:ident: $ty:ident = $init:expr; $($rest:tt)*) => {
+		$crate::define!($(#[$($meta)*])* $vis $mode $id: $ty = $init);
+		$crate::define!($($rest)*);
+	};
+	($(#[$($meta:tt)*])* $vis:vis $mode:ident $id:ident: $ty:ty = $init:expr; $($rest:tt)*) => {
+		$crate::define!($(#[$($meta)*])* $vis $mode $id: $ty = $init);
+		$crate::define!($($rest)*);
 	};
 }
@@ -0,0 +1,166 @@
+//! every parameter created with [`crate::define!`], for listing them and overriding them from strings
+
+use std::error::Error;
+use std::fmt;
+use std::sync::Mutex;
+
+use crate::snapshot::Capture;
+
+#[linkme::distributed_slice]
+pub static PARAMETERS: [Entry];
+
+/// overrides from [`Entry::set`], used as the initial value on threads that haven't read the parameter yet
+static OVERRIDES: Mutex<Vec<(&'static Entry, String)>> = Mutex::new(Vec::new());
+
+#[derive(Debug)]
+pub struct Entry {
+	pub(crate) name: &'static str,
+	pub(crate) module: &'static str,
+	pub(crate) type_name: &'static str,
+	pub(crate) doc: &'static str,
+	/// `None` if the type isn't `Display`
+	pub(crate) read: fn() -> Option<String>,
+	/// `None` if the type isn't `FromStr`
+	pub(crate) set: fn(&str) -> Option<Result<(), String>>,
+	/// `None` for global parameters and types that aren't `Send + Sync`
+	pub(crate) capture: fn() -> Option<&'static dyn Capture>,
+}
+impl Entry {
+	pub fn name(&self) -> &'static str {
+		self.name
+	}
+	pub fn module(&self) -> &'static str {
+		self.module
+	}
+	/// `module::NAME`
+	pub fn path(&self) -> String {
+		format!("{}::{}", self.module, self.name)
+	}
+	/// as written in the definition
+	pub fn type_name(&self) -> &'static str {
+		self.type_name
+	}
+	/// doc comment without the leading spaces
+	pub fn doc(&self) -> String {
+		self.doc
+			.lines()
+			.map(str::trim)
+			.collect::<Vec<_>>()
+			.join("\n")
+	}
+	/// value on the current thread
+	pub fn read(&self) -> Option<String> {
+		(self.read)()
+	}
+	/// sets the value on the current thread and the initial value on threads that haven't read it yet.
+	/// inside a `with` or `set_scoped` scope of the same parameter the current thread gets its old value
+	/// back when the scope ends, only the initial value for other threads stays
+	pub fn set(&'static self, value: &str) -> Result<(), OverrideError> {
+		// validate before storing, so that invalid overrides never reach a thread-local initializer
+		match (self.set)(value) {
+			None => return Err(OverrideError::NotParsable(self.name)),
+			Some(Err(error)) => {
+				return Err(OverrideError::Invalid {
+					name: self.name,
+					error,
+				})
+			}
+			Some(Ok(())) => {}
+		}
+		let mut overrides = OVERRIDES.lock().unwrap_or_else(|e| e.into_inner());
+		overrides.retain(|(entry, _)| !std::ptr::eq(*entry, self));
+		overrides.push((self, value.to_owned()));
+		Ok(())
+	}
+	pub(crate) fn initial<T>(
+		&'static self,
+		parse: fn(&str) -> Option<Result<T, String>>,
+		init: impl FnOnce() -> T,
+	) -> T {
+		let overridden = OVERRIDES
+			.lock()
+			.unwrap_or_else(|e| e.into_inner())
+			.iter()
+			.find(|(entry, _)| std::ptr::eq(*entry, self))
+			.map(|(_, value)| value.clone());
+		match overridden.and_then(|v| parse(&v)) {
+			Some(Ok(v)) => v,
+			_ => init(),
+		}
+	}
+}
+impl fmt::Display for Entry {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "{}: {}", self.path(), self.type_name)?;
+		if let Some(value) = self.read() {
+			write!(f, " = {value}")?;
+		}
+		Ok(())
+	}
+}
+
+pub fn parameters() -> impl Iterator<Item = &'static Entry> {
+	PARAMETERS.iter()
+}
+
+/// by name or by `module::NAME`, a name shared by parameters in several modules needs the path
+pub fn find(name: &str) -> Result<&'static Entry, OverrideError> {
+	if let Some(entry) = parameters().find(|v| v.path() == name) {
+		return Ok(entry);
+	}
+	let mut found = parameters().filter(|v| v.name == name).collect::<Vec<_>>();
+	match found.len() {
+		0 => Err(OverrideError::Unknown(name.to_owned())),
+		1 => Ok(found.remove(0)),
+		_ => {
+			let mut candidates = found.iter().map(|v| v.path()).collect::<Vec<_>>();
+			candidates.sort();
+			Err(OverrideError::Ambiguous {
+				name: name.to_owned(),
+				candidates,
+			})
+		}
+	}
+}
+
+/// applies a `NAME=value` override, e.g. from a `--set` argument
+pub fn set_override(arg: &str) -> Result<(), OverrideError> {
+	let (name, value) = arg
+		.split_once('=')
+		.ok_or_else(|| OverrideError::Syntax(arg.to_owned()))?;
+	find(name.trim())?.set(value.trim())
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OverrideError {
+	/// not of the form `NAME=value`
+	Syntax(String),
+	Unknown(String),
+	/// the name is used in several modules, `candidates` are their paths
+	Ambiguous {
+		name: String,
+		candidates: Vec<String>,
+	},
+	/// the type doesn't implement `FromStr`
+	NotParsable(&'static str),
+	Invalid {
+		name: &'static str,
+		error: String,
+	},
+}
+impl fmt::Display for OverrideError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			Self::Syntax(arg) => write!(f, "expected NAME=value, got {arg:?}"),
+			Self::Unknown(name) => write!(f, "unknown parameter {name:?}"),
+			Self::Ambiguous { name, candidates } => write!(
+				f,
+				"parameter {name:?} is ambiguous, use one of {}",
+				candidates.join(", ")
+			),
+			Self::NotParsable(name) => write!(f, "parameter {name} can't be set from a string"),
+			Self::Invalid { name, error } => write!(f, "invalid value for {name}: {error}"),
+		}
+	}
+}
+impl Error for OverrideError {}
@@ -0,0 +1,46 @@
+//! carrying parameter values over to other threads, e.g. rayon workers
+
+use crate::registry::parameters;
+
+/// parameters whose value can be sent to other threads
+pub trait Capture {
+	fn capture(&'static self) -> Box<dyn Install>;
+}
+
+/// a captured value
+pub trait Install: Send + Sync {
+	/// the returned value restores the old value when dropped
+	fn install(&self) -> Box<dyn Restore>;
+}
+
+pub trait Restore {}
+
+pub(crate) struct Captured<P: 'static, T> {
+	pub(crate) param: &'static P,
+	pub(crate) value: T,
+}
+
+/// values of every thread-local parameter in the [`crate::registry`] on the capturing thread,
+/// parameters with a type that isn't `Send + Sync` are skipped
+pub struct Snapshot(Vec<Box<dyn Install>>);
+impl Snapshot {
+	pub fn capture() -> Self {
+		Self(
+			parameters()
+				.filter_map(|entry| (entry.capture)())
+				.map(|param| param.capture())
+				.collect(),
+		)
+	}
+	/// set every captured value on the current thread until the guard is dropped
+	pub fn install(&self) -> SnapshotGuard {
+		SnapshotGuard(self.0.iter().map(|v| v.install()).collect())
+	}
+}
+
+pub struct SnapshotGuard(Vec<Box<dyn Restore>>);
+impl Drop for SnapshotGuard {
+	fn drop(&mut self) {
+		while self.0.pop().is_some() {}
+	}
+}